@@ -0,0 +1,489 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    asset::{AssetLoader, LoadedAsset},
+    input::mouse::MouseMotion,
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::{Deserialize, Serialize};
+
+/// Something the player does, bound to keys and mouse buttons in a
+/// `.bindings.ron` file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    /// Also swims up in the water.
+    Jump,
+    Sprint,
+    SwimDown,
+    /// Uses the focused interactable, like entering a plane through a door.
+    Interact,
+    ToggleWingsuit,
+    DeployParachute,
+    PitchUp,
+    PitchDown,
+    RollLeft,
+    RollRight,
+    YawLeft,
+    YawRight,
+    /// Also raises the collective of a helicopter.
+    ThrottleUp,
+    ThrottleDown,
+    MixtureRich,
+    MixtureLean,
+    Ignition,
+    Afterburner,
+    Spoilers,
+    Airbrakes,
+    Slats,
+    FuelSelector,
+    /// Selects or deselects an engine, numbered from 1.
+    ToggleEngine(usize),
+    SelectAllEngines,
+    ExitVehicle,
+    HandOverControl,
+    ChangeSeat,
+    /// Switches between flying with the controls and steering towards the
+    /// mouse.
+    ToggleMouseAim,
+    Aerotow,
+    WinchLaunch,
+    ReleaseRope,
+    LockCursor,
+    UnlockCursor,
+    ToggleEditor,
+    ToggleMalfunctions,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// A button of any connected gamepad or joystick.
+    Gamepad(GamepadButtonType),
+}
+
+/// Analog flight control, positive with the stick pushed forward or right,
+/// the right pedal pushed or the throttle lever forward.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlAxis {
+    Pitch,
+    Roll,
+    Yaw,
+    /// An absolute lever, from idle at -1 to full at 1.
+    Throttle,
+}
+
+/// Maps an axis of a gamepad or joystick to a [`ControlAxis`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AxisBinding {
+    pub axis: GamepadAxisType,
+    /// Fraction of the travel around the center, or the idle end of a lever,
+    /// that is ignored.
+    #[serde(default = "default_dead_zone")]
+    pub dead_zone: f32,
+    /// Blend from a linear response at 0 to a cubic one at 1, softening
+    /// small inputs.
+    #[serde(default)]
+    pub expo: f32,
+    #[serde(default)]
+    pub invert: bool,
+}
+
+const fn default_dead_zone() -> f32 {
+    0.05
+}
+
+impl AxisBinding {
+    pub fn new(axis: GamepadAxisType) -> Self {
+        Self {
+            axis,
+            dead_zone: default_dead_zone(),
+            expo: 0.0,
+            invert: false,
+        }
+    }
+
+    /// Applies the dead zone and response curve to a value from 0 to 1.
+    fn curve(&self, value: f32) -> f32 {
+        if value <= self.dead_zone {
+            return 0.0;
+        }
+
+        let value = f32::min((value - self.dead_zone) / (1.0 - self.dead_zone), 1.0);
+
+        (1.0 - self.expo) * value + self.expo * value.powi(3)
+    }
+
+    /// Shapes a raw value from -1 to 1, of a stick centered at 0 or a lever
+    /// idle at -1.
+    pub fn apply(&self, control: ControlAxis, raw: f32) -> f32 {
+        let raw = if self.invert { -raw } else { raw };
+
+        if control == ControlAxis::Throttle {
+            self.curve((raw + 1.0) / 2.0) * 2.0 - 1.0
+        } else {
+            self.curve(raw.abs()) * raw.signum()
+        }
+    }
+}
+
+const fn default_mouse_sensitivity() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, TypeUuid)]
+#[uuid = "3b8e4f0a-9c71-4d25-b6e3-0f5a2d8c1e47"]
+pub struct ControlBindings {
+    #[serde(default = "default_mouse_sensitivity")]
+    pub mouse_sensitivity: f32,
+    #[serde(default)]
+    pub invert_mouse_x: bool,
+    #[serde(default)]
+    pub invert_mouse_y: bool,
+    /// Bindings of each action, actions left out of a file keep their default
+    /// bindings.
+    #[serde(default)]
+    pub actions: HashMap<Action, Vec<Binding>>,
+    /// Analog axes of the connected gamepads and joysticks, axes left out
+    /// of a file keep their default bindings.
+    #[serde(default)]
+    pub axes: HashMap<ControlAxis, AxisBinding>,
+}
+
+impl Default for ControlBindings {
+    fn default() -> Self {
+        let keys = [
+            (Action::MoveForward, KeyCode::W),
+            (Action::MoveBack, KeyCode::S),
+            (Action::MoveLeft, KeyCode::A),
+            (Action::MoveRight, KeyCode::D),
+            (Action::Jump, KeyCode::Space),
+            (Action::Sprint, KeyCode::LShift),
+            (Action::SwimDown, KeyCode::LControl),
+            (Action::Interact, KeyCode::Return),
+            (Action::ToggleWingsuit, KeyCode::F),
+            (Action::DeployParachute, KeyCode::Space),
+            (Action::PitchUp, KeyCode::S),
+            (Action::PitchDown, KeyCode::W),
+            (Action::RollLeft, KeyCode::Q),
+            (Action::RollRight, KeyCode::E),
+            (Action::YawLeft, KeyCode::A),
+            (Action::YawRight, KeyCode::D),
+            (Action::ThrottleUp, KeyCode::LShift),
+            (Action::ThrottleDown, KeyCode::LControl),
+            (Action::MixtureRich, KeyCode::PageUp),
+            (Action::MixtureLean, KeyCode::PageDown),
+            (Action::Ignition, KeyCode::I),
+            (Action::Afterburner, KeyCode::Y),
+            (Action::Spoilers, KeyCode::Z),
+            (Action::Airbrakes, KeyCode::B),
+            (Action::Slats, KeyCode::X),
+            (Action::FuelSelector, KeyCode::T),
+            (Action::SelectAllEngines, KeyCode::Key0),
+            (Action::ExitVehicle, KeyCode::Return),
+            (Action::HandOverControl, KeyCode::C),
+            (Action::ChangeSeat, KeyCode::V),
+            (Action::ToggleMouseAim, KeyCode::M),
+            (Action::Aerotow, KeyCode::G),
+            (Action::WinchLaunch, KeyCode::H),
+            (Action::ReleaseRope, KeyCode::R),
+            (Action::UnlockCursor, KeyCode::Escape),
+            (Action::ToggleEditor, KeyCode::F1),
+            (Action::ToggleMalfunctions, KeyCode::F2),
+        ];
+
+        let engine_keys = [
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+        ];
+
+        let mut actions: HashMap<Action, Vec<Binding>> = keys
+            .into_iter()
+            .map(|(action, key)| (action, vec![Binding::Key(key)]))
+            .collect();
+
+        for (i, key) in engine_keys.into_iter().enumerate() {
+            actions.insert(Action::ToggleEngine(i + 1), vec![Binding::Key(key)]);
+        }
+
+        // the right mouse button locks the cursor, so space is free to jump
+        actions.insert(Action::LockCursor, vec![Binding::Mouse(MouseButton::Right)]);
+
+        // a gamepad has no throttle lever, the triggers move the throttle
+        for (action, button) in [
+            (Action::ThrottleUp, GamepadButtonType::RightTrigger2),
+            (Action::ThrottleDown, GamepadButtonType::LeftTrigger2),
+        ] {
+            actions
+                .entry(action)
+                .or_default()
+                .push(Binding::Gamepad(button));
+        }
+
+        let axes = [
+            (ControlAxis::Pitch, GamepadAxisType::LeftStickY),
+            (ControlAxis::Roll, GamepadAxisType::LeftStickX),
+            (ControlAxis::Yaw, GamepadAxisType::RightStickX),
+        ]
+        .into_iter()
+        .map(|(control, axis)| (control, AxisBinding::new(axis)))
+        .collect();
+
+        Self {
+            mouse_sensitivity: default_mouse_sensitivity(),
+            invert_mouse_x: false,
+            invert_mouse_y: false,
+            actions,
+            axes,
+        }
+    }
+}
+
+pub struct ControlBindingsLoader;
+
+impl AssetLoader for ControlBindingsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::asset::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async {
+            let mut bindings = ron::de::from_bytes::<ControlBindings>(bytes)?;
+
+            let defaults = ControlBindings::default();
+
+            let mut actions = defaults.actions;
+            actions.extend(bindings.actions);
+            bindings.actions = actions;
+
+            let mut axes = defaults.axes;
+            axes.extend(bindings.axes);
+            bindings.axes = axes;
+
+            load_context.set_default_asset(LoadedAsset::new(bindings));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bindings.ron"]
+    }
+}
+
+/// State of every [`Action`] this frame, from the loaded [`ControlBindings`].
+///
+/// The bindings are reloaded when their file changes.
+#[derive(Clone, Debug, Default)]
+pub struct Controls {
+    pub bindings: Handle<ControlBindings>,
+    /// Used until the bindings finish loading.
+    default_bindings: ControlBindings,
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    /// Mouse movement while the cursor is locked, scaled by the sensitivity.
+    pub mouse_delta: Vec2,
+    /// Shaped values of the bound analog axes, while a gamepad is connected.
+    analog: HashMap<ControlAxis, f32>,
+    /// Position of the throttle lever from 0 to 1, only while it moves so
+    /// the keys can still change the throttle.
+    pub throttle_lever: Option<f32>,
+    last_throttle: Option<f32>,
+}
+
+impl Controls {
+    /// Returns the loaded bindings, or the defaults while they load.
+    pub fn bindings<'a>(&'a self, bindings: &'a Assets<ControlBindings>) -> &'a ControlBindings {
+        bindings
+            .get(&self.bindings)
+            .unwrap_or(&self.default_bindings)
+    }
+
+    /// Returns the name of the first key or button bound to `action`, to show
+    /// in prompts.
+    pub fn label(&self, action: Action, bindings: &Assets<ControlBindings>) -> String {
+        match self
+            .bindings(bindings)
+            .actions
+            .get(&action)
+            .and_then(|b| b.first())
+        {
+            Some(Binding::Key(key)) => format!("{:?}", key),
+            Some(Binding::Mouse(button)) => format!("{:?} mouse", button),
+            Some(Binding::Gamepad(button)) => format!("{:?}", button),
+            None => String::from("unbound"),
+        }
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Returns 1 while only `positive` is pressed, -1 while only `negative` is
+    /// pressed and 0 otherwise.
+    pub fn axis(&self, negative: Action, positive: Action) -> f32 {
+        let mut value = 0.0;
+
+        if self.pressed(positive) {
+            value += 1.0;
+        }
+
+        if self.pressed(negative) {
+            value -= 1.0;
+        }
+
+        value
+    }
+
+    /// Returns a flight control from -1 to 1, the keys added to the analog
+    /// axis.
+    ///
+    /// For the throttle only the keys are returned, as a rate of change, the
+    /// lever is read from [`throttle_lever`](Self::throttle_lever).
+    pub fn flight_axis(&self, control: ControlAxis) -> f32 {
+        let keys = match control {
+            ControlAxis::Pitch => self.axis(Action::PitchUp, Action::PitchDown),
+            ControlAxis::Roll => self.axis(Action::RollLeft, Action::RollRight),
+            ControlAxis::Yaw => self.axis(Action::YawLeft, Action::YawRight),
+            ControlAxis::Throttle => {
+                return self.axis(Action::ThrottleDown, Action::ThrottleUp);
+            }
+        };
+
+        let analog = self.analog.get(&control).copied().unwrap_or(0.0);
+
+        f32::clamp(keys + analog, -1.0, 1.0)
+    }
+
+    pub fn system(
+        mut controls: ResMut<Controls>,
+        bindings: Res<Assets<ControlBindings>>,
+        key_input: Res<Input<KeyCode>>,
+        mouse_input: Res<Input<MouseButton>>,
+        gamepads: Res<Gamepads>,
+        gamepad_input: Res<Input<GamepadButton>>,
+        gamepad_axes: Res<Axis<GamepadAxis>>,
+        windows: Res<Windows>,
+        mut mouse_motion: EventReader<MouseMotion>,
+    ) {
+        let controls = &mut *controls;
+
+        // fields are borrowed separately, the bindings are read while the
+        // state is written
+        let bindings = bindings
+            .get(&controls.bindings)
+            .unwrap_or(&controls.default_bindings);
+
+        controls.pressed.clear();
+        controls.just_pressed.clear();
+
+        for (action, action_bindings) in bindings.actions.iter() {
+            for binding in action_bindings.iter() {
+                let (pressed, just_pressed) = match *binding {
+                    Binding::Key(key) => (key_input.pressed(key), key_input.just_pressed(key)),
+                    Binding::Mouse(button) => (
+                        mouse_input.pressed(button),
+                        mouse_input.just_pressed(button),
+                    ),
+                    Binding::Gamepad(button) => {
+                        gamepads
+                            .iter()
+                            .fold((false, false), |(pressed, just_pressed), &gamepad| {
+                                let button = GamepadButton(gamepad, button);
+
+                                (
+                                    pressed || gamepad_input.pressed(button),
+                                    just_pressed || gamepad_input.just_pressed(button),
+                                )
+                            })
+                    }
+                };
+
+                if pressed {
+                    controls.pressed.insert(*action);
+                }
+
+                if just_pressed {
+                    controls.just_pressed.insert(*action);
+                }
+            }
+        }
+
+        let mut delta = Vec2::ZERO;
+
+        if windows.primary().cursor_locked() {
+            for event in mouse_motion.iter() {
+                delta += event.delta;
+            }
+
+            delta *= bindings.mouse_sensitivity / 1000.0;
+        }
+
+        if bindings.invert_mouse_x {
+            delta.x = -delta.x;
+        }
+
+        if bindings.invert_mouse_y {
+            delta.y = -delta.y;
+        }
+
+        controls.mouse_delta = delta;
+
+        controls.analog.clear();
+        controls.throttle_lever = None;
+
+        // with several gamepads connected each axis follows the one moved
+        // furthest from rest, the idle end for the throttle lever
+        for (control, axis) in bindings.axes.iter() {
+            let rest = match control {
+                ControlAxis::Throttle => -1.0,
+                _ => 0.0,
+            };
+
+            let value = gamepads
+                .iter()
+                .filter_map(|&gamepad| gamepad_axes.get(GamepadAxis(gamepad, axis.axis)))
+                .map(|raw| axis.apply(*control, raw))
+                .max_by(|a, b| f32::total_cmp(&(a - rest).abs(), &(b - rest).abs()));
+
+            if let Some(value) = value {
+                controls.analog.insert(*control, value);
+            }
+        }
+
+        let throttle = controls
+            .analog
+            .get(&ControlAxis::Throttle)
+            .map(|lever| (lever + 1.0) / 2.0);
+
+        // a lever that didn't move leaves the throttle to the keys
+        let moved = match (throttle, controls.last_throttle) {
+            (Some(throttle), Some(last)) => (throttle - last).abs() > 0.005,
+            (Some(_), None) => true,
+            _ => false,
+        };
+
+        if moved {
+            controls.throttle_lever = throttle;
+            controls.last_throttle = throttle;
+        }
+    }
+}
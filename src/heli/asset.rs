@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use bevy::asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+
+use super::HeliDescriptor;
+
+pub struct HeliAssetLoader;
+
+impl HeliAssetLoader {
+    /// Parses a descriptor and resolves the polars of its blades, returning
+    /// the descriptor and the paths of the assets it depends on.
+    fn resolve(
+        bytes: &[u8],
+        load_context: &LoadContext,
+    ) -> Result<(HeliDescriptor, Vec<AssetPath<'static>>), anyhow::Error> {
+        let mut descriptor = ron::de::from_bytes::<HeliDescriptor>(bytes)?;
+        let mut dependencies = Vec::new();
+
+        for rotor in descriptor.rotors.iter_mut() {
+            if let Some(ref polar) = rotor.blade.polar {
+                let path = AssetPath::from(PathBuf::from(polar));
+
+                rotor.blade.polar_handle = Some(load_context.get_handle(path.clone()));
+                dependencies.push(path);
+            }
+        }
+
+        Ok((descriptor, dependencies))
+    }
+}
+
+impl AssetLoader for HeliAssetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> bevy::asset::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async {
+            let asset = match Self::resolve(bytes, load_context) {
+                Ok((descriptor, dependencies)) => {
+                    LoadedAsset::new(descriptor).with_dependencies(dependencies)
+                }
+                Err(err) => {
+                    warn!(
+                        "error loading heli '{}': {}",
+                        load_context.path().display(),
+                        err
+                    );
+
+                    LoadedAsset::new(HeliDescriptor::default())
+                }
+            };
+
+            load_context.set_default_asset(asset);
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["heli.ron"]
+    }
+}
@@ -0,0 +1,410 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, reflect::TypeUuid};
+use bevy_inspector_egui::Inspectable;
+use bevy_prototype_debug_lines::DebugLines;
+use heron::{prelude::*, rapier_plugin::PhysicsWorld};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controls::{Action, ControlAxis, Controls},
+    interaction::{Interactable, InteractionEvent},
+    lift::LiftSources,
+    plane::{PlaneBody, PlaneCamera, Polar, SurfaceForces},
+    player::Player,
+    terrain::{TerrainCenter, TerrainChunks},
+};
+
+use super::{Rotor, RotorControls};
+
+const fn default_governor_gain() -> f32 {
+    4.0
+}
+
+/// Turbine driving all rotors through a governor that holds the main rotor at
+/// its nominal speed.
+#[derive(Inspectable, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HeliEngine {
+    /// Power at full output, in watts.
+    pub max_power: f32,
+    /// Speed of the main rotor the governor holds, in rpm.
+    pub nominal_rpm: f32,
+    /// Fraction of full power added per fraction of rotor speed lost.
+    #[serde(default = "default_governor_gain")]
+    pub governor_gain: f32,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "3a9c6b7e-0d41-4f8a-b2e5-91c4d7a6f320"]
+pub struct HeliDescriptor {
+    pub mass: f32,
+    pub center_of_mass: Vec3,
+    /// Rotors, all geared to the speed of the main rotor.
+    pub rotors: Vec<Rotor>,
+    #[serde(default)]
+    pub bodies: Vec<PlaneBody>,
+    pub engine: HeliEngine,
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Heli {
+    pub descriptor: Handle<HeliDescriptor>,
+    pub entered: bool,
+    pub controls: RotorControls,
+    pub engine_running: bool,
+    /// Speed of the main rotor, in rad/s.
+    pub rotor_speed: f32,
+    /// The player flying the helicopter, kept while their entity is
+    /// despawned.
+    pub occupant: Option<Player>,
+}
+
+impl Default for Heli {
+    fn default() -> Self {
+        Self {
+            descriptor: Default::default(),
+            entered: false,
+            controls: RotorControls::default(),
+            engine_running: true,
+            rotor_speed: 0.0,
+            occupant: None,
+        }
+    }
+}
+
+/// Places next to the cabin the player tries to leave at, in heli space.
+const EXIT_OFFSETS: [Vec3; 4] = [
+    Vec3::new(-2.5, 1.0, 1.0),
+    Vec3::new(2.5, 1.0, 1.0),
+    Vec3::new(-2.5, 1.0, -1.5),
+    Vec3::new(2.5, 1.0, -1.5),
+];
+
+impl Heli {
+    pub fn spawn(
+        self,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
+        transform: Transform,
+    ) -> Entity {
+        let descriptor = asset_server.load("helis/basic.heli.ron");
+
+        let material = materials.add(StandardMaterial {
+            base_color: Color::rgb(0.8, 0.3, 0.2),
+            ..Default::default()
+        });
+
+        let cabin = Vec3::new(1.6, 1.8, 3.0);
+        let boom = Vec3::new(0.3, 0.3, 4.0);
+
+        commands
+            .spawn()
+            .insert(transform)
+            .insert(GlobalTransform::identity())
+            .insert(RigidBody::Dynamic)
+            .insert(Velocity::default())
+            .insert(Heli {
+                descriptor,
+                ..Default::default()
+            })
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(PbrBundle {
+                        mesh: meshes.add(Mesh::from(shape::Box::new(cabin.x, cabin.y, cabin.z))),
+                        material: material.clone(),
+                        transform: Transform::from_xyz(0.0, 1.2, 0.5),
+                        ..Default::default()
+                    })
+                    .insert(CollisionShape::Cuboid {
+                        half_extends: cabin / 2.0,
+                        border_radius: None,
+                    })
+                    .insert(Interactable::new("Enter helicopter", 4.0));
+
+                parent
+                    .spawn_bundle(PbrBundle {
+                        mesh: meshes.add(Mesh::from(shape::Box::new(boom.x, boom.y, boom.z))),
+                        material,
+                        transform: Transform::from_xyz(0.0, 1.6, -3.0),
+                        ..Default::default()
+                    })
+                    .insert(CollisionShape::Cuboid {
+                        half_extends: boom / 2.0,
+                        border_radius: None,
+                    });
+            })
+            .id()
+    }
+
+    pub fn debug_system(
+        mut lines: ResMut<DebugLines>,
+        descriptors: Res<Assets<HeliDescriptor>>,
+        query: Query<(&GlobalTransform, &Heli)>,
+    ) {
+        if !cfg!(feature = "debug") {
+            return;
+        }
+
+        for (transform, heli) in query.iter() {
+            let descriptor = if let Some(d) = descriptors.get(&heli.descriptor) {
+                d
+            } else {
+                continue;
+            };
+
+            for rotor in descriptor.rotors.iter() {
+                rotor.debug_draw(transform, Color::BLUE, &mut lines);
+            }
+        }
+    }
+
+    pub fn flight_system(
+        time: Res<Time>,
+        controls: Res<Controls>,
+        descriptors: Res<Assets<HeliDescriptor>>,
+        polars: Res<Assets<Polar>>,
+        lift_sources: Res<LiftSources>,
+        terrain: Res<TerrainChunks>,
+        mut lines: ResMut<DebugLines>,
+        mut query: Query<(&mut Heli, &mut Velocity, &GlobalTransform)>,
+    ) {
+        let elapsed = time.seconds_since_startup() as f32;
+
+        for (mut heli, mut velocity, transform) in query.iter_mut() {
+            let descriptor = if let Some(d) = descriptors.get(&heli.descriptor) {
+                d
+            } else {
+                continue;
+            };
+
+            let heli = &mut *heli;
+
+            if heli.entered {
+                let rotor_controls = &mut heli.controls;
+
+                // the lever sets the collective, the keys move it
+                let collective = rotor_controls.collective
+                    + controls.flight_axis(ControlAxis::Throttle) * 0.5 * time.delta_seconds();
+
+                rotor_controls.collective = controls
+                    .throttle_lever
+                    .unwrap_or(collective)
+                    .clamp(0.0, 1.0);
+
+                rotor_controls.pitch = controls.flight_axis(ControlAxis::Pitch);
+                rotor_controls.roll = -controls.flight_axis(ControlAxis::Roll);
+                rotor_controls.yaw = -controls.flight_axis(ControlAxis::Yaw);
+
+                if controls.just_pressed(Action::Ignition) {
+                    heli.engine_running = !heli.engine_running;
+                }
+            }
+
+            let angular_velocity: Vec3 = velocity.angular.into();
+            let center_of_mass = *transform * descriptor.center_of_mass;
+
+            let mut forces = SurfaceForces::default();
+
+            let lift = lift_sources.around(transform.translation, &terrain);
+
+            let mut rotor_forces = Vec::with_capacity(descriptor.rotors.len());
+            let mut load_torque = 0.0;
+            let mut inertia = 0.0;
+
+            for rotor in descriptor.rotors.iter() {
+                let rotor_speed = heli.rotor_speed * rotor.gear_ratio;
+                let wind = lift.air_velocity(*transform * rotor.position, elapsed);
+
+                let rotor_force = rotor.calculate_forces(
+                    rotor_speed,
+                    &heli.controls,
+                    velocity.linear - wind,
+                    angular_velocity,
+                    center_of_mass,
+                    transform,
+                    rotor.blade.polar(&polars),
+                    &mut lines,
+                );
+
+                forces.linear += rotor_force.linear;
+                forces.angular += rotor_force.angular;
+
+                // torque and inertia as seen from the main rotor shaft
+                load_torque -= rotor_force.shaft_torque * rotor.gear_ratio;
+                inertia += rotor.inertia * rotor.gear_ratio * rotor.gear_ratio;
+
+                rotor_forces.push(rotor_force);
+            }
+
+            for body in descriptor.bodies.iter() {
+                let position = *transform * body.position;
+                let relative_position = position - center_of_mass;
+                let rotation = transform.rotation * body.rotation_quat();
+
+                let air_density = f32::clamp(1.0 - (position.y / 1000.0), 0.0, 1.0);
+                let wind = lift.air_velocity(position, elapsed);
+
+                let body_forces = body.calculate_forces(
+                    wind - velocity.linear - Vec3::cross(angular_velocity, relative_position),
+                    air_density,
+                    relative_position,
+                    position,
+                    rotation,
+                    &mut lines,
+                );
+
+                forces.linear += body_forces.linear;
+                forces.angular += body_forces.angular;
+            }
+
+            let engine = &descriptor.engine;
+            let nominal_speed = engine.nominal_rpm * TAU / 60.0;
+
+            // the governor feeds the current load forward and trims the
+            // remaining speed error
+            let power = if heli.engine_running && nominal_speed > 0.0 {
+                let speed_error = (nominal_speed - heli.rotor_speed) / nominal_speed;
+                let load_power = load_torque * heli.rotor_speed;
+
+                let fraction =
+                    engine.governor_gain * speed_error + load_power / engine.max_power.max(1.0);
+
+                fraction.clamp(0.0, 1.0) * engine.max_power
+            } else {
+                0.0
+            };
+
+            let engine_torque = power / f32::max(heli.rotor_speed, 0.2 * nominal_speed);
+            let rotor_acceleration = if inertia > 0.0 {
+                (engine_torque - load_torque) / inertia
+            } else {
+                0.0
+            };
+
+            heli.rotor_speed = f32::max(
+                heli.rotor_speed + rotor_acceleration * time.delta_seconds(),
+                0.0,
+            );
+
+            // every shaft turns its rotor by pushing back on the airframe
+            for (rotor, rotor_force) in descriptor.rotors.iter().zip(rotor_forces.iter()) {
+                let shaft_torque = rotor.inertia * rotor.gear_ratio * rotor_acceleration
+                    - rotor_force.shaft_torque;
+
+                forces.angular -= rotor_force.spin_axis * shaft_torque;
+            }
+
+            if cfg!(feature = "debug") {
+                lines.line_colored(
+                    center_of_mass,
+                    center_of_mass + forces.linear * 0.01,
+                    0.0,
+                    Color::YELLOW,
+                );
+            }
+
+            velocity.linear += forces.linear * time.delta_seconds() / descriptor.mass;
+            velocity.angular = From::from(
+                angular_velocity + forces.angular * time.delta_seconds() / descriptor.mass,
+            );
+        }
+    }
+
+    pub fn enter_system(
+        mut commands: Commands,
+        controls: Res<Controls>,
+        descriptors: Res<Assets<HeliDescriptor>>,
+        physics_world: PhysicsWorld,
+        plane_camera_query: Query<(Entity, &Parent), With<PlaneCamera>>,
+        mut heli_query: Query<(Entity, &mut Heli, &GlobalTransform)>,
+        velocity_query: Query<&Velocity, With<Heli>>,
+        player_query: Query<&Player>,
+        parent_query: Query<&Parent>,
+        mut interaction_events: EventReader<InteractionEvent>,
+    ) {
+        let entered = heli_query
+            .iter()
+            .find(|(_, heli, _)| heli.entered)
+            .map(|(entity, _, _)| entity);
+
+        if let Some(heli_entity) = entered {
+            if !controls.just_pressed(Action::ExitVehicle) {
+                return;
+            }
+
+            let (_, mut heli, heli_transform) = heli_query.get_mut(heli_entity).unwrap();
+
+            let candidates: Vec<Vec3> = EXIT_OFFSETS
+                .iter()
+                .map(|offset| *heli_transform * *offset)
+                .collect();
+
+            let center_of_mass = descriptors
+                .get(&heli.descriptor)
+                .map_or(Vec3::ZERO, |descriptor| descriptor.center_of_mass);
+
+            let exited = Player::exit_vehicle(
+                &mut commands,
+                &physics_world,
+                &candidates,
+                velocity_query.get(heli_entity).ok(),
+                *heli_transform * center_of_mass,
+                || heli.occupant.take().unwrap_or_default(),
+            );
+
+            if exited.is_none() {
+                return;
+            }
+
+            commands.entity(heli_entity).remove::<TerrainCenter>();
+
+            heli.entered = false;
+
+            for (entity, parent) in plane_camera_query.iter() {
+                if parent.0 == heli_entity {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+        } else {
+            for event in interaction_events.iter() {
+                // the cabin is interacted with, it is a child of the heli
+                let heli_entity = match parent_query.get(event.target) {
+                    Ok(parent) => parent.0,
+                    Err(_) => continue,
+                };
+
+                let player = match player_query.get(event.player) {
+                    Ok(player) => player,
+                    Err(_) => continue,
+                };
+
+                let mut heli = match heli_query.get_mut(heli_entity) {
+                    Ok((_, heli, _)) => heli,
+                    Err(_) => continue,
+                };
+
+                if heli.occupant.is_some() {
+                    continue;
+                }
+
+                commands.entity(event.player).despawn_recursive();
+
+                heli.entered = true;
+                heli.occupant = Some(player.clone());
+
+                commands
+                    .entity(heli_entity)
+                    .insert(TerrainCenter)
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(PerspectiveCameraBundle::default())
+                            .insert(PlaneCamera::default());
+                    });
+
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,7 @@
+mod asset;
+mod heli;
+mod rotor;
+
+pub use asset::*;
+pub use heli::*;
+pub use rotor::*;
@@ -0,0 +1,289 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use bevy_prototype_debug_lines::DebugLines;
+use serde::{Deserialize, Serialize};
+
+use crate::plane::{PlaneSurface, Polar};
+
+/// Which controls set the blade pitch of a rotor.
+///
+/// `Main` rotors follow the collective and cyclic, `Tail` rotors follow the
+/// pedals.
+#[derive(Inspectable, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotorKind {
+    Main,
+    Tail,
+}
+
+impl Default for RotorKind {
+    fn default() -> Self {
+        Self::Main
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RotorControls {
+    /// Collective from 0 to 1.
+    pub collective: f32,
+    pub pitch: f32,
+    pub roll: f32,
+    pub yaw: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RotorForces {
+    pub linear: Vec3,
+    /// Torque on the airframe, without the torque about the shaft.
+    pub angular: Vec3,
+    /// Aerodynamic torque about the shaft in the direction of rotation,
+    /// negative while the air slows the rotor down.
+    pub shaft_torque: f32,
+    /// Direction of rotation in world space.
+    pub spin_axis: Vec3,
+}
+
+fn default_axis() -> Vec3 {
+    Vec3::Y
+}
+
+const fn default_root_cutout() -> f32 {
+    0.15
+}
+
+const fn default_gear_ratio() -> f32 {
+    1.0
+}
+
+const fn default_elements() -> usize {
+    6
+}
+
+const fn default_azimuth_samples() -> usize {
+    8
+}
+
+/// A rotor whose blades are split into [`PlaneSurface`] elements.
+///
+/// Forces are averaged over a number of azimuth positions, so the rotor acts
+/// like a disk and doesn't need to be stepped at blade passing frequency.
+#[derive(Inspectable, Clone, Debug, Serialize, Deserialize)]
+pub struct Rotor {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub kind: RotorKind,
+    /// Position of the hub.
+    pub position: Vec3,
+    /// Axis the rotor spins around, thrust points along it at positive pitch.
+    #[serde(default = "default_axis")]
+    pub axis: Vec3,
+    /// Whether the rotor turns clockwise seen from the tip of its axis.
+    #[serde(default)]
+    pub clockwise: bool,
+    pub blades: usize,
+    pub radius: f32,
+    /// Fraction of the radius at the root without blade.
+    #[serde(default = "default_root_cutout")]
+    pub root_cutout: f32,
+    /// Airfoil of the blades, span, position and rotation are set by the
+    /// rotor.
+    pub blade: PlaneSurface,
+    /// Change of blade pitch from root to tip, in degrees.
+    #[serde(default)]
+    pub twist: f32,
+    /// Blade pitch at the bottom of the collective, or full right pedal for a
+    /// tail rotor, in degrees.
+    pub min_pitch: f32,
+    /// Blade pitch at the top of the collective, or full left pedal for a
+    /// tail rotor, in degrees.
+    pub max_pitch: f32,
+    /// Blade pitch change at full cyclic, in degrees.
+    #[serde(default)]
+    pub cyclic_pitch: f32,
+    /// Speed of the rotor relative to the main rotor.
+    #[serde(default = "default_gear_ratio")]
+    pub gear_ratio: f32,
+    /// Moment of inertia about the shaft, in kg m².
+    pub inertia: f32,
+    /// Number of blade elements along each blade.
+    #[serde(default = "default_elements")]
+    pub elements: usize,
+    /// Number of azimuth positions forces are averaged over.
+    #[serde(default = "default_azimuth_samples")]
+    pub azimuth_samples: usize,
+}
+
+impl Default for Rotor {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            kind: RotorKind::Main,
+            position: Vec3::ZERO,
+            axis: default_axis(),
+            clockwise: false,
+            blades: 2,
+            radius: 1.0,
+            root_cutout: default_root_cutout(),
+            blade: PlaneSurface::default(),
+            twist: 0.0,
+            min_pitch: 0.0,
+            max_pitch: 10.0,
+            cyclic_pitch: 0.0,
+            gear_ratio: default_gear_ratio(),
+            inertia: 1.0,
+            elements: default_elements(),
+            azimuth_samples: default_azimuth_samples(),
+        }
+    }
+}
+
+impl Rotor {
+    fn spin(&self) -> f32 {
+        if self.clockwise {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns the hub position and spin axis in world space, and the
+    /// directions at azimuth 0 and 90 degrees.
+    ///
+    /// Azimuth is measured from the forward direction, or from up for rotors
+    /// spinning around the longitudinal axis.
+    fn disk(&self, transform: &GlobalTransform) -> (Vec3, Vec3, Vec3, Vec3) {
+        let hub = *transform * self.position;
+        let axis = (transform.rotation * self.axis).normalize_or_zero();
+
+        let mut reference = transform.local_z() - axis * axis.dot(transform.local_z());
+
+        if reference.length_squared() < 0.01 {
+            reference = transform.local_y() - axis * axis.dot(transform.local_y());
+        }
+
+        let reference = reference.normalize_or_zero();
+        let side = axis.cross(reference);
+
+        (hub, axis, reference, side)
+    }
+
+    /// Returns the blade pitch at the root, in degrees, before cyclic.
+    pub fn collective_pitch(&self, controls: &RotorControls) -> f32 {
+        let t = match self.kind {
+            RotorKind::Main => controls.collective,
+            RotorKind::Tail => 0.5 + 0.5 * controls.yaw,
+        };
+
+        self.min_pitch + (self.max_pitch - self.min_pitch) * t.clamp(0.0, 1.0)
+    }
+
+    /// Computes the forces of all blades, `rotor_speed` is the speed of this
+    /// rotor in rad/s and `velocity` the velocity of the airframe relative to
+    /// the air.
+    pub fn calculate_forces(
+        &self,
+        rotor_speed: f32,
+        controls: &RotorControls,
+        velocity: Vec3,
+        angular_velocity: Vec3,
+        center_of_mass: Vec3,
+        transform: &GlobalTransform,
+        polar: Option<&Polar>,
+        lines: &mut DebugLines,
+    ) -> RotorForces {
+        let spin = self.spin();
+        let (hub, axis, reference, side) = self.disk(transform);
+        let rotor_angular_velocity = axis * spin * rotor_speed;
+
+        let blade_length = self.radius * (1.0 - self.root_cutout);
+        let elements = self.elements.max(1);
+        let samples = self.azimuth_samples.max(1);
+        let scale = self.blades as f32 / (elements * samples) as f32;
+
+        // the whole blade is used for the aspect ratio, each element takes
+        // its share of the force through `scale`
+        let mut blade = self.blade.clone();
+        blade.span = blade_length;
+
+        let air_density = f32::clamp(1.0 - (hub.y / 1000.0), 0.0, 1.0);
+        let collective_pitch = self.collective_pitch(controls);
+
+        let mut forces = RotorForces {
+            spin_axis: axis * spin,
+            ..Default::default()
+        };
+
+        for sample in 0..samples {
+            let azimuth = TAU * sample as f32 / samples as f32;
+            let radial = reference * azimuth.cos() + side * azimuth.sin();
+            let motion = axis.cross(radial) * spin;
+
+            let mut pitch = collective_pitch;
+
+            if self.kind == RotorKind::Main {
+                // forward cyclic lifts the rear of the disk, roll lowers the
+                // side it rolls towards
+                pitch -= self.cyclic_pitch * controls.pitch * azimuth.cos();
+                pitch -= self.cyclic_pitch * controls.roll * azimuth.sin();
+            }
+
+            let frame = Quat::from_mat3(&Mat3::from_cols(-radial * spin, axis, motion));
+
+            for element in 0..elements {
+                let fraction = (element as f32 + 0.5) / elements as f32;
+                let distance = self.radius * self.root_cutout + blade_length * fraction;
+
+                let position = hub + radial * distance;
+                let relative_position = position - center_of_mass;
+                let element_pitch = (pitch + self.twist * fraction).to_radians();
+                let rotation = frame * Quat::from_rotation_x(-element_pitch);
+
+                let air_velocity = -velocity
+                    - Vec3::cross(angular_velocity, relative_position)
+                    - Vec3::cross(rotor_angular_velocity, position - hub);
+
+                let element_forces = blade.calculate_forces(
+                    air_velocity,
+                    air_density,
+                    relative_position,
+                    position,
+                    rotation,
+                    0.0,
+                    0.0,
+                    polar,
+                    lines,
+                );
+
+                let linear = element_forces.linear * scale;
+                let shaft_torque = axis.dot(Vec3::cross(position - hub, linear));
+
+                forces.linear += linear;
+                forces.angular += element_forces.angular * scale - axis * shaft_torque;
+                forces.shaft_torque += shaft_torque * spin;
+            }
+        }
+
+        forces
+    }
+
+    /// Draws the disk of the rotor.
+    pub fn debug_draw(&self, transform: &GlobalTransform, color: Color, lines: &mut DebugLines) {
+        let (hub, axis, reference, side) = self.disk(transform);
+
+        const SEGMENTS: usize = 24;
+
+        for i in 0..SEGMENTS {
+            let a = TAU * i as f32 / SEGMENTS as f32;
+            let b = TAU * (i + 1) as f32 / SEGMENTS as f32;
+
+            let start = hub + (reference * a.cos() + side * a.sin()) * self.radius;
+            let end = hub + (reference * b.cos() + side * b.sin()) * self.radius;
+
+            lines.line_colored(start, end, 0.0, color);
+        }
+
+        lines.line_colored(hub, hub + axis, 0.0, color);
+    }
+}
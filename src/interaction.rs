@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContext, egui};
+
+use crate::{
+    controls::{Action, ControlBindings, Controls},
+    player::{Player, PlayerCamera},
+};
+
+/// Something the player can use by pressing interact while focused on it, like
+/// the door of a plane or an item lying around.
+#[derive(Component, Clone, Debug)]
+pub struct Interactable {
+    /// Shown while the player is focused on it.
+    pub prompt: String,
+    /// Furthest distance from the eyes of the player, in m.
+    pub range: f32,
+    /// Whether the player has to look at it, being in range is enough
+    /// otherwise.
+    pub look_at: bool,
+    /// Disabled interactables can't be focused, like a door without a free
+    /// seat behind it.
+    pub enabled: bool,
+}
+
+impl Default for Interactable {
+    fn default() -> Self {
+        Self {
+            prompt: String::from("Use"),
+            range: 2.0,
+            look_at: true,
+            enabled: true,
+        }
+    }
+}
+
+impl Interactable {
+    pub fn new(prompt: impl Into<String>, range: f32) -> Self {
+        Self {
+            prompt: prompt.into(),
+            range,
+            ..Default::default()
+        }
+    }
+}
+
+/// Sent when `player` uses the [`Interactable`] `target`.
+#[derive(Clone, Copy, Debug)]
+pub struct InteractionEvent {
+    pub player: Entity,
+    pub target: Entity,
+}
+
+/// The [`Interactable`] the player is focused on.
+#[derive(Clone, Debug, Default)]
+pub struct InteractionFocus {
+    pub target: Option<Entity>,
+}
+
+/// Largest angle between the view of the player and an interactable they
+/// look at, in degrees.
+const LOOK_ANGLE: f32 = 20.0;
+
+impl InteractionFocus {
+    /// Focuses the interactable in range closest to the center of the view
+    /// of the player.
+    pub fn system(
+        mut focus: ResMut<InteractionFocus>,
+        camera_query: Query<&GlobalTransform, With<PlayerCamera>>,
+        interactable_query: Query<(Entity, &Interactable, &GlobalTransform)>,
+    ) {
+        focus.target = None;
+
+        let camera = if let Ok(camera) = camera_query.get_single() {
+            camera
+        } else {
+            return;
+        };
+
+        let eye = camera.translation;
+        let forward = -camera.local_z();
+
+        let mut nearest: Option<(Entity, f32)> = None;
+
+        for (entity, interactable, transform) in interactable_query.iter() {
+            if !interactable.enabled {
+                continue;
+            }
+
+            let offset = transform.translation - eye;
+            let distance = offset.length();
+
+            if distance > interactable.range {
+                continue;
+            }
+
+            let angle = if distance > 0.0 {
+                forward.angle_between(offset)
+            } else {
+                0.0
+            };
+
+            if interactable.look_at && angle > LOOK_ANGLE.to_radians() {
+                continue;
+            }
+
+            if nearest.map_or(true, |(_, nearest)| angle < nearest) {
+                nearest = Some((entity, angle));
+            }
+        }
+
+        focus.target = nearest.map(|(entity, _)| entity);
+    }
+
+    pub fn interact_system(
+        controls: Res<Controls>,
+        focus: Res<InteractionFocus>,
+        player_query: Query<Entity, With<Player>>,
+        mut events: EventWriter<InteractionEvent>,
+    ) {
+        if !controls.just_pressed(Action::Interact) {
+            return;
+        }
+
+        if let (Some(target), Ok(player)) = (focus.target, player_query.get_single()) {
+            events.send(InteractionEvent { player, target });
+        }
+    }
+
+    /// Shows the prompt of the focused interactable below the center of the
+    /// screen.
+    pub fn prompt_system(
+        mut egui_context: ResMut<EguiContext>,
+        focus: Res<InteractionFocus>,
+        controls: Res<Controls>,
+        bindings: Res<Assets<ControlBindings>>,
+        interactable_query: Query<&Interactable>,
+    ) {
+        let interactable = match focus
+            .target
+            .and_then(|target| interactable_query.get(target).ok())
+        {
+            Some(interactable) => interactable,
+            None => return,
+        };
+
+        let key = controls.label(Action::Interact, &bindings);
+
+        egui::Area::new("interaction_prompt")
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 40.0))
+            .show(egui_context.ctx_mut(), |ui| {
+                ui.label(format!("[{}] {}", key, interactable.prompt));
+            });
+    }
+}
@@ -0,0 +1,227 @@
+use std::f32::consts::TAU;
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_prototype_debug_lines::DebugLines;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::terrain::{TerrainCenter, TerrainChunks};
+
+/// A column of rising air, seeded from the key of a terrain chunk.
+#[derive(Clone, Copy, Debug)]
+pub struct Thermal {
+    /// Where the thermal leaves the ground.
+    pub base: Vec3,
+    pub radius: f32,
+    /// Climb rate in the core at full strength, in m/s.
+    pub strength: f32,
+    /// Height above the base where the thermal dies out.
+    pub top: f32,
+    /// Length of a cycle from building up to fading away, in seconds.
+    pub period: f32,
+    pub phase: f32,
+}
+
+impl Thermal {
+    /// Returns the center of the thermal at `height`, drifted downwind.
+    pub fn center(&self, height: f32, wind: Vec3) -> Vec3 {
+        let rise = f32::max(height - self.base.y, 0.0);
+
+        self.base + thermal_drift(rise, wind) + Vec3::Y * rise
+    }
+
+    /// Returns the strength of the thermal at `time`, the thermal is
+    /// inactive for part of every cycle.
+    pub fn current_strength(&self, time: f32) -> f32 {
+        let cycle = f32::sin(TAU * time / self.period + self.phase);
+
+        self.strength * f32::max(cycle, 0.0).sqrt()
+    }
+
+    pub fn vertical_velocity(&self, position: Vec3, wind: Vec3, time: f32) -> f32 {
+        let rise = position.y - self.base.y;
+
+        if rise < 0.0 || rise > self.top {
+            return 0.0;
+        }
+
+        let center = self.center(position.y, wind);
+        let distance = position.xz().distance(center.xz()) / self.radius;
+
+        // the thermal weakens over the top 100 m
+        let fade = f32::min((self.top - rise) / 100.0, 1.0);
+
+        self.current_strength(time) * f32::exp(-distance * distance) * fade
+    }
+}
+
+/// Sources of rising air: ridge lift where the wind meets slopes, and
+/// thermals.
+#[derive(Clone, Debug)]
+pub struct LiftSources {
+    /// Wind near the ground, in m/s.
+    pub wind: Vec3,
+    /// Height above the ground over which ridge lift fades out.
+    pub ridge_height: f32,
+    /// Chance of a terrain chunk having a thermal.
+    pub thermal_chance: f32,
+    pub seed: u64,
+}
+
+impl Default for LiftSources {
+    fn default() -> Self {
+        Self {
+            wind: Vec3::ZERO,
+            ridge_height: 60.0,
+            thermal_chance: 0.06,
+            seed: 0,
+        }
+    }
+}
+
+/// Chunks around a position that are searched for thermals.
+const THERMAL_SEARCH_RANGE: i32 = 2;
+
+/// Climb rate used to find how far thermals lean downwind, in m/s.
+const THERMAL_DRIFT_CLIMB_RATE: f32 = 3.0;
+
+/// Returns how far air that rose by `rise` drifted with the wind.
+fn thermal_drift(rise: f32, wind: Vec3) -> Vec3 {
+    Vec3::new(wind.x, 0.0, wind.z) * rise / THERMAL_DRIFT_CLIMB_RATE
+}
+
+impl LiftSources {
+    /// Returns the thermal of the chunk at `key`, if it has one.
+    pub fn thermal(&self, key: IVec2, terrain: &TerrainChunks) -> Option<Thermal> {
+        let hash = (key.x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ^ (key.y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+            ^ self.seed;
+        let mut rng = StdRng::seed_from_u64(hash);
+
+        if rng.gen::<f32>() >= self.thermal_chance {
+            return None;
+        }
+
+        let chunk_size = terrain.chunk_size as f32;
+        let offset = Vec2::new(rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5)) * chunk_size;
+        let position = key.as_vec2() + offset;
+
+        let sample = terrain.sample(position)?;
+
+        Some(Thermal {
+            base: Vec3::new(position.x, sample.height, position.y),
+            radius: rng.gen_range(30.0..80.0),
+            strength: rng.gen_range(1.5..4.0),
+            top: rng.gen_range(600.0..1200.0),
+            period: rng.gen_range(300.0..900.0),
+            phase: rng.gen_range(0.0..TAU),
+        })
+    }
+
+    /// Calls `f` with every thermal that may reach `position`.
+    pub fn thermals_near(
+        &self,
+        position: Vec3,
+        terrain: &TerrainChunks,
+        mut f: impl FnMut(Thermal),
+    ) {
+        // thermals reaching the position left the ground upwind of it
+        let origin = position - thermal_drift(position.y, self.wind);
+        let center = terrain.chunk_key(origin.xz());
+
+        for x in -THERMAL_SEARCH_RANGE..=THERMAL_SEARCH_RANGE {
+            for z in -THERMAL_SEARCH_RANGE..=THERMAL_SEARCH_RANGE {
+                let key = center + IVec2::new(x, z) * terrain.chunk_size;
+
+                if let Some(thermal) = self.thermal(key, terrain) {
+                    f(thermal);
+                }
+            }
+        }
+    }
+
+    /// Air deflected upwards by the slope under `position`.
+    pub fn ridge_lift(&self, position: Vec3, terrain: &TerrainChunks) -> f32 {
+        let sample = if let Some(sample) = terrain.sample(position.xz()) {
+            sample
+        } else {
+            return 0.0;
+        };
+
+        let height = position.y - sample.height;
+
+        if height < 0.0 {
+            return 0.0;
+        }
+
+        // wind following the surface keeps the part along the slope, whose
+        // vertical component is the lift
+        let into_slope = -Vec3::dot(self.wind, sample.normal);
+        let lift = f32::max(into_slope * sample.normal.y, 0.0);
+
+        lift * f32::exp(-height / self.ridge_height)
+    }
+
+    /// Finds the thermals that may reach a vehicle at `position`, once for
+    /// all the points sampled on it.
+    pub fn around<'a>(&'a self, position: Vec3, terrain: &'a TerrainChunks) -> LocalLift<'a> {
+        let mut thermals = Vec::new();
+        self.thermals_near(position, terrain, |thermal| thermals.push(thermal));
+
+        LocalLift {
+            sources: self,
+            terrain,
+            thermals,
+        }
+    }
+
+    pub fn debug_system(
+        time: Res<Time>,
+        lift_sources: Res<LiftSources>,
+        terrain: Res<TerrainChunks>,
+        mut lines: ResMut<DebugLines>,
+        query: Query<&GlobalTransform, With<TerrainCenter>>,
+    ) {
+        if !cfg!(feature = "debug") {
+            return;
+        }
+
+        let time = time.seconds_since_startup() as f32;
+
+        for transform in query.iter() {
+            lift_sources.thermals_near(transform.translation, &terrain, |thermal| {
+                let color = if thermal.current_strength(time) > 0.0 {
+                    Color::ORANGE
+                } else {
+                    Color::GRAY
+                };
+
+                let top = thermal.center(thermal.base.y + thermal.top, lift_sources.wind);
+
+                lines.line_colored(thermal.base, top, 0.0, color);
+            });
+        }
+    }
+}
+
+/// The lift sources around a vehicle, with the thermals that may reach it.
+pub struct LocalLift<'a> {
+    sources: &'a LiftSources,
+    terrain: &'a TerrainChunks,
+    thermals: Vec<Thermal>,
+}
+
+impl LocalLift<'_> {
+    /// Returns the velocity of the air at `position`.
+    pub fn air_velocity(&self, position: Vec3, time: f32) -> Vec3 {
+        let wind = self.sources.wind;
+
+        let thermal_lift: f32 = self
+            .thermals
+            .iter()
+            .map(|thermal| thermal.vertical_velocity(position, wind, time))
+            .sum();
+        let lift = self.sources.ridge_lift(position, self.terrain) + thermal_lift;
+
+        wind + Vec3::Y * lift
+    }
+}
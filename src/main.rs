@@ -1,17 +1,37 @@
 mod collision_from_mesh;
+mod controls;
+mod heli;
+mod interaction;
+mod lift;
+mod parachute;
 mod plane;
 mod player;
 mod sun;
 mod terrain;
+mod tow;
+mod water;
 mod window;
+mod wingsuit;
 
-use bevy::prelude::*;
+use bevy::{input::InputSystem, prelude::*};
+use bevy_inspector_egui::bevy_egui::EguiPlugin;
 use bevy_prototype_debug_lines::*;
+use controls::{ControlBindings, ControlBindingsLoader, Controls};
+use heli::{Heli, HeliAssetLoader, HeliDescriptor};
 use heron::prelude::*;
-use plane::{Plane, PlaneAssetLoader, PlaneCamera, PlaneDescriptor};
+use interaction::{InteractionEvent, InteractionFocus};
+use lift::LiftSources;
+use parachute::Parachute;
+use plane::{
+    Autopilot, MalfunctionMenu, MouseAim, Plane, PlaneAssetLoader, PlaneCamera, PlaneDescriptor,
+    PlaneEditor, Polar, PolarAssetLoader, Scenario, ScenarioAssetLoader,
+};
 use player::Player;
 use sun::SunLight;
 use terrain::{HeightMap, TerrainChunks};
+use tow::TowRope;
+use water::Water;
+use wingsuit::Wingsuit;
 
 fn main() {
     App::new()
@@ -19,22 +39,60 @@ fn main() {
         .add_plugins(DefaultPlugins)
         .add_plugin(PhysicsPlugin::default())
         .add_plugin(DebugLinesPlugin::default())
+        .add_plugin(EguiPlugin)
         // assets
         .add_asset::<HeightMap>()
+        .add_asset::<ControlBindings>()
+        .add_asset_loader(ControlBindingsLoader)
         .add_asset::<PlaneDescriptor>()
         .add_asset_loader(PlaneAssetLoader)
+        .add_asset::<Polar>()
+        .add_asset_loader(PolarAssetLoader)
+        .add_asset::<HeliDescriptor>()
+        .add_asset_loader(HeliAssetLoader)
+        .add_asset::<Scenario>()
+        .add_asset_loader(ScenarioAssetLoader)
         // resources
+        .init_resource::<Controls>()
         .init_resource::<TerrainChunks>()
+        .init_resource::<InteractionFocus>()
+        .init_resource::<LiftSources>()
+        .init_resource::<Water>()
+        .init_resource::<PlaneEditor>()
+        .init_resource::<MalfunctionMenu>()
         .insert_resource(Gravity::from(Vec3::new(0.0, -9.81, 0.0)))
+        // events
+        .add_event::<InteractionEvent>()
         // startup systems
         .add_startup_system(setup)
         // systems
+        .add_system_to_stage(CoreStage::PreUpdate, Controls::system.after(InputSystem))
         .add_system(TerrainChunks::system)
         .add_system(Player::system)
+        .add_system(Player::health_system)
+        .add_system(Parachute::system)
+        .add_system(Wingsuit::system)
+        .add_system(InteractionFocus::system)
+        .add_system(InteractionFocus::interact_system)
+        .add_system(InteractionFocus::prompt_system)
+        .add_system(Plane::door_system)
         .add_system(Plane::enter_system)
+        .add_system(Autopilot::system)
+        .add_system(MouseAim::system)
         .add_system(Plane::flight_system)
         .add_system(Plane::debug_system)
+        .add_system(Heli::enter_system)
+        .add_system(Heli::flight_system)
+        .add_system(Heli::debug_system)
         .add_system(PlaneCamera::system)
+        .add_system(PlaneEditor::toggle_system)
+        .add_system(PlaneEditor::system)
+        .add_system(MalfunctionMenu::toggle_system)
+        .add_system(MalfunctionMenu::system)
+        .add_system(TowRope::launch_system)
+        .add_system(TowRope::system)
+        .add_system(LiftSources::debug_system)
+        .add_system(Water::system)
         .add_system(SunLight::system)
         .add_system(window::window_system)
         .add_system(collision_from_mesh::pending_colliders_system)
@@ -44,11 +102,17 @@ fn main() {
 
 fn setup(
     mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     asset_server: Res<AssetServer>,
+    terrain: Res<TerrainChunks>,
+    water: Res<Water>,
+    mut controls: ResMut<Controls>,
 ) {
     asset_server.watch_for_changes().unwrap();
 
+    controls.bindings = asset_server.load("controls.bindings.ron");
+
     materials.set_untracked(
         TerrainChunks::MATERIAL,
         StandardMaterial {
@@ -60,8 +124,30 @@ fn setup(
     Plane::default().spawn(
         &mut commands,
         &asset_server,
+        "planes/basic.plane.ron",
         Transform::from_xyz(0.0, 15.0, -4.0),
     );
+    Plane::default().spawn(
+        &mut commands,
+        &asset_server,
+        "planes/seaplane.plane.ron",
+        Transform::from_xyz(-12.0, 15.0, -4.0),
+    );
+    Plane::default().spawn(
+        &mut commands,
+        &asset_server,
+        "planes/twin.plane.ron",
+        Transform::from_xyz(0.0, 15.0, -16.0),
+    );
+    Heli::default().spawn(
+        &mut commands,
+        &asset_server,
+        &mut meshes,
+        &mut materials,
+        Transform::from_xyz(12.0, 15.0, -4.0),
+    );
+
+    water.spawn(&mut commands, &terrain, &mut meshes, &mut materials);
 
     commands
         .spawn_bundle(DirectionalLightBundle {
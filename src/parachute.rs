@@ -0,0 +1,269 @@
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::DebugLines;
+use heron::{prelude::*, rapier_plugin::PhysicsWorld};
+
+use crate::{
+    controls::{Action, Controls},
+    lift::LiftSources,
+    player::{Player, PLAYER_MASS},
+    terrain::TerrainChunks,
+    water::Water,
+    wingsuit::Wingsuit,
+};
+
+/// State of a [`Parachute`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CanopyState {
+    /// Still packed, the player is in free fall.
+    Packed,
+    /// Inflating, from 0 when the parachute is pulled to 1 when it is open.
+    Deploying(f32),
+    Open,
+}
+
+impl Default for CanopyState {
+    fn default() -> Self {
+        Self::Packed
+    }
+}
+
+/// A steerable parachute worn by a player falling through the air.
+///
+/// The component is removed once the player lands.
+#[derive(Component, Clone, Debug)]
+pub struct Parachute {
+    pub state: CanopyState,
+    /// Area of the open canopy, in m².
+    pub canopy_area: f32,
+    pub lift_coefficient: f32,
+    pub drag_coefficient: f32,
+    /// Added lift and drag coefficient with both brakes pulled.
+    pub brake_coefficient: f32,
+    /// Time to inflate, in seconds.
+    pub deploy_time: f32,
+    /// Turn rate with one brake pulled, in rad/s.
+    pub turn_rate: f32,
+    /// Velocity in the last update, used to find the landing impact.
+    pub last_velocity: Vec3,
+    /// Seconds since the player started falling.
+    pub elapsed: f32,
+}
+
+impl Default for Parachute {
+    fn default() -> Self {
+        Self {
+            state: CanopyState::Packed,
+            canopy_area: 22.0,
+            lift_coefficient: 0.7,
+            drag_coefficient: 0.3,
+            brake_coefficient: 0.4,
+            deploy_time: 2.5,
+            turn_rate: 0.8,
+            last_velocity: Vec3::ZERO,
+            elapsed: 0.0,
+        }
+    }
+}
+
+/// Area times drag coefficient of a player in free fall, in m².
+const FREE_FALL_DRAG_AREA: f32 = 0.5;
+
+/// Landing speed below which the player is unhurt, in m/s.
+const SAFE_LANDING_SPEED: f32 = 6.0;
+
+/// Landing speed at which the player is knocked out, in m/s.
+const FATAL_LANDING_SPEED: f32 = 16.0;
+
+/// Time after bailing out before the player can land, so they clear the
+/// vehicle they left.
+const MIN_FALL_TIME: f32 = 0.5;
+
+/// Height of the canopy above the player.
+const CANOPY_HEIGHT: f32 = 5.0;
+
+impl Parachute {
+    /// Sets a player who just left a vehicle in flight falling with
+    /// `velocity`, with their parachute still packed.
+    pub fn bail_out(commands: &mut Commands, player: Entity, velocity: Vec3) {
+        commands
+            .entity(player)
+            .insert(Velocity::from_linear(velocity))
+            .insert(Parachute::default());
+    }
+
+    /// Returns how far the canopy is open, from 0 to 1.
+    pub fn inflation(&self) -> f32 {
+        match self.state {
+            CanopyState::Packed => 0.0,
+            CanopyState::Deploying(t) => t,
+            CanopyState::Open => 1.0,
+        }
+    }
+
+    /// Computes the force of the canopy and the falling body, `air_velocity`
+    /// is the velocity of the air relative to the player, `right` points to
+    /// the right of the canopy and `brake` is from 0 to 1.
+    pub fn calculate_force(
+        &self,
+        air_velocity: Vec3,
+        air_density: f32,
+        right: Vec3,
+        brake: f32,
+    ) -> Vec3 {
+        let speed = air_velocity.length();
+        let dynamic_pressure = 0.5 * air_density * speed * speed;
+
+        let inflation = self.inflation();
+        let area = self.canopy_area * inflation;
+
+        let drag_coefficient = self.drag_coefficient + self.brake_coefficient * brake;
+        let lift_coefficient = self.lift_coefficient + self.brake_coefficient * brake;
+
+        // the canopy glides like a wing flying into the air, lift pushes it
+        // forward while it sinks
+        let lift_direction = Vec3::cross(air_velocity, right).normalize_or_zero();
+
+        let drag = air_velocity.normalize_or_zero()
+            * dynamic_pressure
+            * (FREE_FALL_DRAG_AREA + area * drag_coefficient);
+        let lift = lift_direction * dynamic_pressure * area * lift_coefficient;
+
+        drag + lift
+    }
+
+    pub fn system(
+        mut commands: Commands,
+        time: Res<Time>,
+        controls: Res<Controls>,
+        lift_sources: Res<LiftSources>,
+        terrain: Res<TerrainChunks>,
+        water: Res<Water>,
+        physics_world: PhysicsWorld,
+        mut lines: ResMut<DebugLines>,
+        mut query: Query<(
+            Entity,
+            &mut Player,
+            &mut Parachute,
+            &mut Velocity,
+            &mut Transform,
+            &GlobalTransform,
+            Option<&Wingsuit>,
+        )>,
+    ) {
+        let elapsed = time.seconds_since_startup() as f32;
+        let dt = time.delta_seconds();
+
+        for (
+            entity,
+            mut player,
+            mut parachute,
+            mut velocity,
+            mut transform,
+            global_transform,
+            wingsuit,
+        ) in query.iter_mut()
+        {
+            let position = global_transform.translation;
+            parachute.elapsed += dt;
+
+            // landed on the ground or in the water
+            let grounded = Player::is_grounded(&physics_world, position);
+            let landed = grounded || water.depth(position) > 0.0;
+
+            if landed && parachute.elapsed > MIN_FALL_TIME {
+                let impact_speed = -parachute.last_velocity.y;
+                let damage = (impact_speed - SAFE_LANDING_SPEED)
+                    / (FATAL_LANDING_SPEED - SAFE_LANDING_SPEED);
+
+                // water takes some of the blow
+                let damage = if grounded { damage } else { damage * 0.5 };
+
+                player.health = f32::max(player.health - damage.max(0.0), 0.0);
+
+                commands.entity(entity).remove::<Parachute>();
+
+                continue;
+            }
+
+            match parachute.state {
+                CanopyState::Packed if controls.just_pressed(Action::DeployParachute) => {
+                    parachute.state = CanopyState::Deploying(0.0);
+                }
+                CanopyState::Deploying(t) => {
+                    let t = t + dt / parachute.deploy_time;
+
+                    parachute.state = if t >= 1.0 {
+                        CanopyState::Open
+                    } else {
+                        CanopyState::Deploying(t)
+                    };
+                }
+                _ => {}
+            }
+
+            let mut left_brake = 0.0;
+            let mut right_brake = 0.0;
+
+            if parachute.state == CanopyState::Open {
+                if controls.pressed(Action::MoveLeft) {
+                    left_brake = 1.0;
+                }
+
+                if controls.pressed(Action::MoveRight) {
+                    right_brake = 1.0;
+                }
+
+                if controls.pressed(Action::MoveBack) {
+                    left_brake = 1.0;
+                    right_brake = 1.0;
+                }
+
+                // pulling one brake slows that side of the canopy down
+                let turn = (left_brake - right_brake) * parachute.turn_rate * dt;
+                transform.rotate(Quat::from_rotation_y(turn));
+            }
+
+            let brake = (left_brake + right_brake) / 2.0;
+            let right = global_transform.local_x();
+
+            let air_density = f32::clamp(1.0 - (position.y / 1000.0), 0.0, 1.0);
+            let wind = lift_sources
+                .around(position, &terrain)
+                .air_velocity(position, elapsed);
+
+            // a flying wingsuit already drags the body, and the canopy is
+            // still packed
+            if !wingsuit.map_or(false, |wingsuit| wingsuit.flying) {
+                let force =
+                    parachute.calculate_force(wind - velocity.linear, air_density, right, brake);
+
+                velocity.linear += force * dt / PLAYER_MASS;
+            }
+
+            parachute.last_velocity = velocity.linear;
+
+            let inflation = parachute.inflation();
+
+            if inflation > 0.0 {
+                let forward = -global_transform.local_z();
+                let top = position + Vec3::Y * CANOPY_HEIGHT * inflation;
+                let half_span = right * 4.0 * inflation;
+                let half_chord = forward * 1.5 * inflation;
+
+                let corners = [
+                    top + half_span + half_chord,
+                    top - half_span + half_chord,
+                    top - half_span - half_chord,
+                    top + half_span - half_chord,
+                ];
+
+                for i in 0..corners.len() {
+                    let next = corners[(i + 1) % corners.len()];
+
+                    lines.line_colored(corners[i], next, 0.0, Color::ORANGE);
+                    lines.line_colored(position, corners[i], 0.0, Color::WHITE);
+                }
+            }
+        }
+    }
+}
@@ -1,32 +1,424 @@
+use std::{path::PathBuf, sync::Arc};
+
 use bevy::{
-    asset::{AssetLoader, LoadedAsset},
-    prelude::warn,
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::*,
+    utils::HashSet,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    mirror_name, CgEnvelope, Door, FuelTank, PayloadStation, PlaneBody, PlaneDescriptor,
+    PlaneEngine, PlaneFloat, PlaneSurface, Seat, SurfaceInputKind, SurfaceKind, SurfaceSide,
 };
-use serde::Deserialize;
 
-use super::PlaneDescriptor;
+/// Extension that lets optional fields of a descriptor file be written
+/// without `Some(..)`, enabled for every file read and written.
+const IMPLICIT_SOME: &[u8] = b"#![enable(implicit_some)]";
+
+/// Returns `value` if it differs from `base`.
+fn changed<T: Clone + PartialEq>(base: &T, value: &T) -> Option<T> {
+    (base != value).then(|| value.clone())
+}
+
+/// Returns the value of an optional field if it is set and differs from
+/// `base`. A file can't unset a field of its base, so clearing one is an
+/// error.
+fn changed_option<T: Clone + PartialEq>(
+    name: &str,
+    base: &Option<T>,
+    value: &Option<T>,
+) -> Result<Option<T>, anyhow::Error> {
+    if base.is_some() && value.is_none() {
+        anyhow::bail!("{} is set by the base and can't be cleared", name);
+    }
+
+    Ok(value.clone().filter(|value| base.as_ref() != Some(value)))
+}
+
+/// A `.plane.ron` file as it is written on disk.
+///
+/// A file may name a `base` descriptor, in which case every field it leaves
+/// out is taken from the base, and surfaces of the base can be patched by
+/// name.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaneDescriptorFile {
+    pub base: Option<String>,
+    pub mass: Option<f32>,
+    pub center_of_mass: Option<Vec3>,
+    pub surfaces: Option<Vec<PlaneSurface>>,
+    pub surface_patches: Vec<SurfacePatch>,
+    pub added_surfaces: Vec<PlaneSurface>,
+    pub removed_surfaces: Vec<String>,
+    pub bodies: Option<Vec<PlaneBody>>,
+    pub engines: Option<Vec<PlaneEngine>>,
+    pub fuel_tanks: Option<Vec<FuelTank>>,
+    pub payload_stations: Option<Vec<PayloadStation>>,
+    pub cg_envelope: Option<CgEnvelope>,
+    pub floats: Option<Vec<PlaneFloat>>,
+    pub tow_hook: Option<Vec3>,
+    pub tail_hook: Option<Vec3>,
+    pub seats: Option<Vec<Seat>>,
+    pub doors: Option<Vec<Door>>,
+}
+
+impl PlaneDescriptorFile {
+    /// Returns the file that loads as `descriptor`. A descriptor loaded from a
+    /// `base` keeps it and only holds what differs from it.
+    pub fn from_descriptor(descriptor: &PlaneDescriptor) -> Result<Self, anyhow::Error> {
+        let default = PlaneDescriptor::default();
+
+        let (base_path, base) = match descriptor.base {
+            Some((ref path, ref base)) => (Some(path.clone()), base.as_ref()),
+            None => (None, &default),
+        };
+
+        let mut file = Self {
+            base: base_path,
+            mass: changed(&base.mass, &descriptor.mass),
+            center_of_mass: changed(&base.center_of_mass, &descriptor.center_of_mass),
+            bodies: changed(&base.bodies, &descriptor.bodies),
+            engines: changed(&base.engines, &descriptor.engines),
+            fuel_tanks: changed(&base.fuel_tanks, &descriptor.fuel_tanks),
+            payload_stations: changed(&base.payload_stations, &descriptor.payload_stations),
+            cg_envelope: changed_option("cg_envelope", &base.cg_envelope, &descriptor.cg_envelope)?,
+            floats: changed(&base.floats, &descriptor.floats),
+            tow_hook: changed_option("tow_hook", &base.tow_hook, &descriptor.tow_hook)?,
+            tail_hook: changed_option("tail_hook", &base.tail_hook, &descriptor.tail_hook)?,
+            seats: changed(&base.seats, &descriptor.seats),
+            doors: changed(&base.doors, &descriptor.doors),
+            ..Default::default()
+        };
+
+        if descriptor.base.is_none() {
+            file.surfaces = Some(descriptor.surfaces.clone());
+
+            return Ok(file);
+        }
+
+        let mut patched = HashSet::default();
+
+        for surface in descriptor.surfaces.iter() {
+            let base_surface = base
+                .surfaces
+                .iter()
+                .find(|base_surface| base_surface.name == surface.name);
+
+            match base_surface {
+                // a patch can't remove the polar of a surface, or patch two
+                // surfaces of the same name differently
+                Some(base_surface)
+                    if (base_surface.polar.is_none() || surface.polar.is_some())
+                        && patched.insert(surface.name.as_str()) =>
+                {
+                    file.surface_patches
+                        .extend(SurfacePatch::diff(base_surface, surface));
+                }
+                _ => file.added_surfaces.push(surface.clone()),
+            }
+        }
+
+        file.removed_surfaces = base
+            .surfaces
+            .iter()
+            .filter(|surface| !patched.contains(surface.name.as_str()))
+            .map(|surface| surface.name.clone())
+            .collect();
+
+        Ok(file)
+    }
+
+    pub fn apply(&self, descriptor: &mut PlaneDescriptor) {
+        if let Some(mass) = self.mass {
+            descriptor.mass = mass;
+        }
+
+        if let Some(center_of_mass) = self.center_of_mass {
+            descriptor.center_of_mass = center_of_mass;
+        }
+
+        if let Some(ref surfaces) = self.surfaces {
+            descriptor.surfaces = surfaces.clone();
+        }
+
+        // a twin that is removed or patched on its own becomes a surface of
+        // its own
+        for name in self.removed_surfaces.iter() {
+            split_twin(&mut descriptor.surfaces, name);
+        }
+
+        for patch in self.surface_patches.iter() {
+            split_twin(&mut descriptor.surfaces, &patch.name);
+        }
+
+        descriptor
+            .surfaces
+            .retain(|surface| !self.removed_surfaces.contains(&surface.name));
+
+        for patch in self.surface_patches.iter() {
+            let mut found = false;
+
+            for surface in descriptor.surfaces.iter_mut() {
+                if surface.name == patch.name {
+                    patch.apply(surface);
+                    found = true;
+                }
+            }
+
+            if !found {
+                warn!("surface patch for unknown surface '{}'", patch.name);
+            }
+        }
+
+        descriptor
+            .surfaces
+            .extend(self.added_surfaces.iter().cloned());
+
+        if let Some(ref bodies) = self.bodies {
+            descriptor.bodies = bodies.clone();
+        }
+
+        if let Some(ref engines) = self.engines {
+            descriptor.engines = engines.clone();
+        }
+
+        if let Some(ref fuel_tanks) = self.fuel_tanks {
+            descriptor.fuel_tanks = fuel_tanks.clone();
+        }
+
+        if let Some(ref payload_stations) = self.payload_stations {
+            descriptor.payload_stations = payload_stations.clone();
+        }
+
+        if let Some(ref cg_envelope) = self.cg_envelope {
+            descriptor.cg_envelope = Some(cg_envelope.clone());
+        }
+
+        if let Some(ref floats) = self.floats {
+            descriptor.floats = floats.clone();
+        }
+
+        if let Some(tow_hook) = self.tow_hook {
+            descriptor.tow_hook = Some(tow_hook);
+        }
+
+        if let Some(tail_hook) = self.tail_hook {
+            descriptor.tail_hook = Some(tail_hook);
+        }
+
+        if let Some(ref seats) = self.seats {
+            descriptor.seats = seats.clone();
+        }
+
+        if let Some(ref doors) = self.doors {
+            descriptor.doors = doors.clone();
+        }
+    }
+}
+
+/// Stops generating the twin of the mirrored surface named `name` and
+/// inserts the twin as a surface of its own, if such a twin exists.
+fn split_twin(surfaces: &mut Vec<PlaneSurface>, name: &str) {
+    let index = surfaces
+        .iter()
+        .position(|surface| surface.mirror && mirror_name(&surface.name) == name);
+
+    if let Some(index) = index {
+        let twin = surfaces[index].mirrored();
+
+        surfaces[index].mirror = false;
+        surfaces.insert(index + 1, twin);
+    }
+}
+
+/// Overrides for a single surface of a base descriptor, addressed by name.
+///
+/// Patching a mirrored surface also changes its twin, patching the twin by
+/// its name only changes the twin.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurfacePatch {
+    pub name: String,
+    pub kind: Option<SurfaceKind>,
+    pub mirror: Option<bool>,
+    pub groups: Option<Vec<String>>,
+    pub input_kind: Option<SurfaceInputKind>,
+    pub side: Option<SurfaceSide>,
+    pub position: Option<Vec3>,
+    pub rotation: Option<Vec3>,
+    pub lift: Option<f32>,
+    pub span: Option<f32>,
+    pub chord: Option<f32>,
+    pub lift_slope: Option<f32>,
+    pub skin_friction: Option<f32>,
+    pub zero_lift_aoa: Option<f32>,
+    pub stall_angle_high: Option<f32>,
+    pub stall_angle_low: Option<f32>,
+    pub flap_fraction: Option<f32>,
+    pub max_deployment: Option<f32>,
+    pub slat_stall_angle: Option<f32>,
+    pub polar: Option<String>,
+}
+
+impl SurfacePatch {
+    /// Returns the patch that turns `base` into `surface`, or `None` if they
+    /// are the same.
+    pub fn diff(base: &PlaneSurface, surface: &PlaneSurface) -> Option<Self> {
+        let patch = Self {
+            name: surface.name.clone(),
+            kind: changed(&base.kind, &surface.kind),
+            mirror: changed(&base.mirror, &surface.mirror),
+            groups: changed(&base.groups, &surface.groups),
+            input_kind: changed(&base.input_kind, &surface.input_kind),
+            side: changed(&base.side, &surface.side),
+            position: changed(&base.position, &surface.position),
+            rotation: changed(&base.rotation, &surface.rotation),
+            lift: changed(&base.lift, &surface.lift),
+            span: changed(&base.span, &surface.span),
+            chord: changed(&base.chord, &surface.chord),
+            lift_slope: changed(&base.lift_slope, &surface.lift_slope),
+            skin_friction: changed(&base.skin_friction, &surface.skin_friction),
+            zero_lift_aoa: changed(&base.zero_lift_aoa, &surface.zero_lift_aoa),
+            stall_angle_high: changed(&base.stall_angle_high, &surface.stall_angle_high),
+            stall_angle_low: changed(&base.stall_angle_low, &surface.stall_angle_low),
+            flap_fraction: changed(&base.flap_fraction, &surface.flap_fraction),
+            max_deployment: changed(&base.max_deployment, &surface.max_deployment),
+            slat_stall_angle: changed(&base.slat_stall_angle, &surface.slat_stall_angle),
+            // a cleared polar is saved as a new surface instead of a patch
+            polar: surface
+                .polar
+                .clone()
+                .filter(|polar| base.polar.as_ref() != Some(polar)),
+        };
+
+        let unchanged = Self {
+            name: surface.name.clone(),
+            ..Default::default()
+        };
+
+        (patch != unchanged).then(|| patch)
+    }
+
+    pub fn apply(&self, surface: &mut PlaneSurface) {
+        fn set<T: Clone>(target: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *target = value.clone();
+            }
+        }
+
+        set(&mut surface.kind, &self.kind);
+        set(&mut surface.mirror, &self.mirror);
+        set(&mut surface.groups, &self.groups);
+        set(&mut surface.input_kind, &self.input_kind);
+        set(&mut surface.side, &self.side);
+        set(&mut surface.position, &self.position);
+        set(&mut surface.rotation, &self.rotation);
+        set(&mut surface.lift, &self.lift);
+        set(&mut surface.span, &self.span);
+        set(&mut surface.chord, &self.chord);
+        set(&mut surface.lift_slope, &self.lift_slope);
+        set(&mut surface.skin_friction, &self.skin_friction);
+        set(&mut surface.zero_lift_aoa, &self.zero_lift_aoa);
+        set(&mut surface.stall_angle_high, &self.stall_angle_high);
+        set(&mut surface.stall_angle_low, &self.stall_angle_low);
+        set(&mut surface.flap_fraction, &self.flap_fraction);
+        set(&mut surface.max_deployment, &self.max_deployment);
+        set(&mut surface.slat_stall_angle, &self.slat_stall_angle);
+
+        if let Some(ref polar) = self.polar {
+            surface.polar = Some(polar.clone());
+        }
+    }
+}
 
 pub struct PlaneAssetLoader;
 
+impl PlaneAssetLoader {
+    fn parse(bytes: &[u8]) -> Result<PlaneDescriptorFile, anyhow::Error> {
+        let bytes = [IMPLICIT_SOME, bytes].concat();
+        let mut deserializer = ron::Deserializer::from_bytes(&bytes)?;
+
+        Ok(PlaneDescriptorFile::deserialize(&mut deserializer)?)
+    }
+
+    /// Reads `bytes` and every base descriptor it names, returning the
+    /// resolved descriptor and the paths of the assets it depends on.
+    async fn resolve<'a>(
+        bytes: &'a [u8],
+        load_context: &'a LoadContext<'_>,
+    ) -> Result<(PlaneDescriptor, Vec<AssetPath<'static>>), anyhow::Error> {
+        let mut visited = HashSet::default();
+        visited.insert(load_context.path().to_path_buf());
+
+        let mut layers = vec![Self::parse(bytes)?];
+        let mut dependencies = Vec::new();
+
+        while let Some(base) = layers.last().unwrap().base.clone() {
+            let path = PathBuf::from(base);
+
+            if !visited.insert(path.clone()) {
+                anyhow::bail!("plane descriptor base cycle at '{}'", path.display());
+            }
+
+            let bytes = load_context.read_asset_bytes(&path).await?;
+            layers.push(Self::parse(&bytes)?);
+            dependencies.push(AssetPath::from(path));
+        }
+
+        let mut descriptor = PlaneDescriptor::default();
+
+        for layer in layers.iter().skip(1).rev() {
+            layer.apply(&mut descriptor);
+        }
+
+        // keep the base so the editor can save only what differs from it
+        let base = layers[0]
+            .base
+            .clone()
+            .map(|path| (path, Arc::new(descriptor.clone())));
+
+        layers[0].apply(&mut descriptor);
+        descriptor.base = base;
+
+        for surface in descriptor.surfaces.iter_mut() {
+            if let Some(ref polar) = surface.polar {
+                let path = AssetPath::from(PathBuf::from(polar));
+
+                surface.polar_handle = Some(load_context.get_handle(path.clone()));
+                dependencies.push(path);
+            }
+        }
+
+        descriptor.expand_surfaces();
+
+        Ok((descriptor, dependencies))
+    }
+}
+
 impl AssetLoader for PlaneAssetLoader {
     fn load<'a>(
         &'a self,
         bytes: &'a [u8],
-        load_context: &'a mut bevy::asset::LoadContext,
+        load_context: &'a mut LoadContext,
     ) -> bevy::asset::BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async {
-            let mut deserializer = ron::Deserializer::from_bytes(bytes)?;
-
-            let plane_descriptor = match PlaneDescriptor::deserialize(&mut deserializer) {
-                Ok(d) => d,
+            let asset = match Self::resolve(bytes, load_context).await {
+                Ok((descriptor, dependencies)) => {
+                    LoadedAsset::new(descriptor).with_dependencies(dependencies)
+                }
                 Err(err) => {
-                    warn!("error loading plane: {}", err);
+                    warn!(
+                        "error loading plane '{}': {}",
+                        load_context.path().display(),
+                        err
+                    );
 
-                    PlaneDescriptor::default()
+                    LoadedAsset::new(PlaneDescriptor::default())
                 }
             };
 
-            load_context.set_default_asset(LoadedAsset::new(plane_descriptor));
+            load_context.set_default_asset(asset);
 
             Ok(())
         })
@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+use heron::prelude::*;
+
+use super::Plane;
+
+/// Flies a plane the player doesn't have the controls of, holding a heading
+/// and a climb angle.
+#[derive(Component, Clone, Debug)]
+pub struct Autopilot {
+    /// Direction to fly in, only the horizontal part is used.
+    pub heading: Vec3,
+    /// Climb angle once airborne, in degrees.
+    pub climb_angle: f32,
+    pub throttle: f32,
+    /// Airspeed below which the plane keeps rolling along the ground.
+    pub rotate_speed: f32,
+}
+
+impl Default for Autopilot {
+    fn default() -> Self {
+        Self {
+            heading: Vec3::Z,
+            climb_angle: 6.0,
+            throttle: 1.0,
+            rotate_speed: 25.0,
+        }
+    }
+}
+
+/// Largest bank angle the autopilot turns with, in degrees.
+const MAX_BANK: f32 = 25.0;
+
+impl Autopilot {
+    /// Holds the current heading in level flight at the current throttle, for
+    /// a crew handed the controls of `plane`.
+    pub fn holding(plane: &Plane, transform: &GlobalTransform) -> Self {
+        let throttle = plane
+            .engines
+            .iter()
+            .map(|engine| engine.throttle)
+            .fold(0.0, f32::max);
+
+        Self {
+            heading: transform.local_z(),
+            climb_angle: 0.0,
+            throttle,
+            ..Default::default()
+        }
+    }
+
+    /// Turns around and descends, used once the plane has done its job.
+    pub fn return_home(&mut self) {
+        self.heading = -self.heading;
+        self.climb_angle = -3.0;
+        self.throttle = 0.4;
+    }
+
+    pub fn system(mut query: Query<(&Autopilot, &mut Plane, &Velocity, &GlobalTransform)>) {
+        for (autopilot, mut plane, velocity, transform) in query.iter_mut() {
+            if plane.has_control() {
+                continue;
+            }
+
+            let forward = transform.local_z();
+            let left = transform.local_x();
+            let angular_velocity: Vec3 = velocity.angular.into();
+
+            let pitch = forward.y.clamp(-1.0, 1.0).asin();
+            let nose_up_rate = -angular_velocity.dot(left);
+
+            let climb_angle = if velocity.linear.length() < autopilot.rotate_speed {
+                0.0
+            } else {
+                autopilot.climb_angle.to_radians()
+            };
+
+            // positive pitch input lowers the nose
+            plane.input.pitch = ((pitch - climb_angle) * 3.0 + nose_up_rate).clamp(-1.0, 1.0);
+
+            let heading = Vec3::new(autopilot.heading.x, 0.0, autopilot.heading.z);
+            let flat_forward = Vec3::new(forward.x, 0.0, forward.z);
+
+            // positive when the heading is to the left
+            let heading_error =
+                f32::atan2(flat_forward.cross(heading).y, flat_forward.dot(heading));
+
+            let max_bank = MAX_BANK.to_radians();
+            let target_bank = heading_error.clamp(-max_bank, max_bank);
+            let bank_left = (-left.y).clamp(-1.0, 1.0).asin();
+
+            // positive roll input rolls to the left
+            plane.input.roll = ((target_bank - bank_left) * 2.0).clamp(-1.0, 1.0);
+            plane.input.yaw = 0.0;
+
+            for engine in plane.engines.iter_mut() {
+                engine.throttle = autopilot.throttle;
+            }
+        }
+    }
+}
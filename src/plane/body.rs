@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use bevy_prototype_debug_lines::DebugLines;
+use serde::{Deserialize, Serialize};
+
+use super::SurfaceForces;
+
+/// A non-lifting bluff body, like the fuselage, landing gear or a strut.
+///
+/// Drag is computed separately along each local axis, flow along the local z
+/// axis sees the frontal area and flow along the x and y axes sees the side
+/// area.
+#[derive(Inspectable, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlaneBody {
+    #[serde(default)]
+    pub name: String,
+    pub position: Vec3,
+    #[serde(default)]
+    pub rotation: Vec3,
+    pub frontal_area: f32,
+    pub side_area: f32,
+    pub drag_coefficient: f32,
+    pub side_drag_coefficient: f32,
+    /// Side force per radian of sideslip.
+    #[serde(default)]
+    pub side_force_coefficient: f32,
+}
+
+impl Default for PlaneBody {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            position: Vec3::ZERO,
+            rotation: Vec3::ZERO,
+            frontal_area: 0.0,
+            side_area: 0.0,
+            drag_coefficient: 0.5,
+            side_drag_coefficient: 1.0,
+            side_force_coefficient: 0.0,
+        }
+    }
+}
+
+impl PlaneBody {
+    pub fn rotation_quat(&self) -> Quat {
+        Quat::from_euler(
+            EulerRot::YXZ,
+            self.rotation.y.to_radians(),
+            self.rotation.x.to_radians(),
+            self.rotation.z.to_radians(),
+        )
+    }
+
+    pub fn calculate_forces(
+        &self,
+        world_air_velocity: Vec3,
+        air_density: f32,
+        relative_position: Vec3,
+        position: Vec3,
+        rotation: Quat,
+        lines: &mut DebugLines,
+    ) -> SurfaceForces {
+        let air_velocity = rotation.conjugate() * world_air_velocity;
+        let speed = air_velocity.length();
+        let dynamic_pressure = 0.5 * air_density * speed * speed;
+
+        let drag = Vec3::new(
+            self.side_drag_coefficient * self.side_area * air_velocity.x,
+            self.side_drag_coefficient * self.side_area * air_velocity.y,
+            self.drag_coefficient * self.frontal_area * air_velocity.z,
+        ) * 0.5
+            * air_density
+            * speed;
+
+        let sideslip = f32::atan2(air_velocity.x, air_velocity.z.abs());
+        let side_force =
+            Vec3::X * self.side_force_coefficient * sideslip * dynamic_pressure * self.side_area;
+
+        let linear = rotation * (drag + side_force);
+
+        if cfg!(feature = "debug") {
+            lines.line_colored(position, position + linear * 0.01, 0.0, Color::RED);
+        }
+
+        let angular = Vec3::cross(relative_position, linear);
+
+        SurfaceForces { linear, angular }
+    }
+}
@@ -1,11 +1,17 @@
 use std::f32::consts::FRAC_PI_2;
 
-use bevy::{input::mouse::MouseMotion, prelude::*};
+use bevy::prelude::*;
+
+use crate::controls::Controls;
+
+use super::{MouseAim, Plane};
 
 #[derive(Component, Clone, Debug)]
 pub struct PlaneCamera {
     pub distance: f32,
     pub angles: Vec2,
+    /// Point the camera orbits around, in plane space.
+    pub target: Vec3,
 }
 
 impl Default for PlaneCamera {
@@ -13,30 +19,40 @@ impl Default for PlaneCamera {
         Self {
             distance: 15.0,
             angles: Vec2::new(0.0, 0.4),
+            target: Vec3::ZERO,
         }
     }
 }
 
+/// Distance of the point the camera looks at along the mouse aim.
+const AIM_LOOK_DISTANCE: f32 = 500.0;
+
 impl PlaneCamera {
     pub fn system(
-        mut mouse_motion: EventReader<MouseMotion>,
-        windows: Res<Windows>,
-        mut query: Query<(&mut PlaneCamera, &mut Transform)>,
+        controls: Res<Controls>,
+        mut query: Query<(&mut PlaneCamera, &mut Transform, &Parent)>,
+        aim_query: Query<(&Plane, &MouseAim, &GlobalTransform)>,
     ) {
-        let window = windows.primary();
+        if let Ok((mut camera, mut transform, parent)) = query.get_single_mut() {
+            // with mouse aim the camera looks along the aim from behind the
+            // plane, the mouse moves the aim instead
+            if let Ok((plane, mouse_aim, plane_transform)) = aim_query.get(parent.0) {
+                if plane.has_control() && mouse_aim.enabled {
+                    let eye =
+                        -mouse_aim.direction * camera.distance + Vec3::Y * camera.distance * 0.2;
+                    let look = Transform::from_translation(eye)
+                        .looking_at(mouse_aim.direction * AIM_LOOK_DISTANCE, Vec3::Y);
 
-        let mut delta = Vec2::ZERO;
+                    let inverse = plane_transform.rotation.inverse();
 
-        if window.cursor_locked() {
-            for event in mouse_motion.iter() {
-                delta += event.delta;
-            }
+                    transform.translation = camera.target + inverse * eye;
+                    transform.rotation = inverse * look.rotation;
 
-            delta /= 1000.0;
-        }
+                    return;
+                }
+            }
 
-        if let Ok((mut camera, mut transform)) = query.get_single_mut() {
-            camera.angles += delta;
+            camera.angles += controls.mouse_delta;
 
             let y = camera.angles.y.clamp(-FRAC_PI_2, FRAC_PI_2);
             camera.angles.y = y;
@@ -46,8 +62,9 @@ impl PlaneCamera {
             transform.translation.z = -camera.angles.x.cos() * camera.angles.y.cos();
 
             transform.translation *= camera.distance;
+            transform.translation += camera.target;
 
-            transform.look_at(Vec3::ZERO, Vec3::Y);
+            transform.look_at(camera.target, Vec3::Y);
         }
     }
 }
@@ -0,0 +1,359 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{
+    bevy_egui::EguiContext,
+    egui::{self, Ui},
+    Context, Inspectable,
+};
+use bevy_prototype_debug_lines::DebugLines;
+use ron::{extensions::Extensions, ser::PrettyConfig};
+
+use crate::controls::{Action, Controls};
+
+use super::{CgEnvelope, Plane, PlaneDescriptor, PlaneDescriptorFile, PlaneSurface};
+
+/// Live editor for the descriptor of the active plane.
+///
+/// The active plane is the one the player has entered, or the first plane if
+/// none is entered.
+#[derive(Clone, Debug, Default)]
+pub struct PlaneEditor {
+    pub open: bool,
+    pub selected: Option<usize>,
+    /// Group whose surfaces are highlighted.
+    pub group: Option<String>,
+    pub status: String,
+}
+
+impl PlaneEditor {
+    pub fn toggle_system(
+        controls: Res<Controls>,
+        mut windows: ResMut<Windows>,
+        mut editor: ResMut<PlaneEditor>,
+    ) {
+        if controls.just_pressed(Action::ToggleEditor) {
+            editor.open = !editor.open;
+
+            if editor.open {
+                let window = windows.primary_mut();
+                window.set_cursor_lock_mode(false);
+                window.set_cursor_visibility(true);
+            }
+        }
+    }
+
+    pub fn system(
+        mut egui_context: ResMut<EguiContext>,
+        mut editor: ResMut<PlaneEditor>,
+        asset_server: Res<AssetServer>,
+        mut descriptors: ResMut<Assets<PlaneDescriptor>>,
+        mut lines: ResMut<DebugLines>,
+        mut query: Query<(Entity, &mut Plane, &GlobalTransform)>,
+    ) {
+        if !editor.open {
+            return;
+        }
+
+        let active = query
+            .iter()
+            .find(|(_, plane, _)| plane.entered)
+            .or_else(|| query.iter().next())
+            .map(|(entity, _, _)| entity);
+
+        let (mut plane, transform) = match active.and_then(|entity| query.get_mut(entity).ok()) {
+            Some((_, plane, transform)) => (plane, transform),
+            None => return,
+        };
+
+        let mut descriptor = if let Some(d) = descriptors.get(&plane.descriptor) {
+            d.clone()
+        } else {
+            return;
+        };
+
+        let mut open = editor.open;
+        let mut changed = false;
+        let mut save = false;
+
+        egui::Window::new("Plane editor")
+            .open(&mut open)
+            .default_width(320.0)
+            .show(egui_context.ctx_mut(), |ui| {
+                changed |= editor.descriptor_ui(ui, &mut descriptor);
+
+                ui.separator();
+
+                ui.collapsing("Weight and balance", |ui| {
+                    Self::weight_and_balance_ui(ui, &descriptor, &mut plane);
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    save = ui.button("Save").clicked();
+                    ui.label(&editor.status);
+                });
+            });
+
+        editor.open = open;
+
+        if let Some(selected) = editor.selected {
+            if selected >= descriptor.surfaces.len() {
+                editor.selected = None;
+            }
+        }
+
+        for (i, surface) in descriptor.surfaces.iter().enumerate() {
+            let mirrored = surface.mirror.then(|| surface.mirrored());
+
+            for surface in std::iter::once(surface).chain(mirrored.as_ref()) {
+                let in_group = match editor.group {
+                    Some(ref group) => surface.in_group(group),
+                    None => false,
+                };
+
+                if editor.selected == Some(i) || in_group {
+                    surface.debug_draw(transform, Color::ORANGE_RED, Color::YELLOW, &mut lines);
+                } else {
+                    surface.debug_draw(transform, Color::BLUE, Color::YELLOW_GREEN, &mut lines);
+                }
+            }
+        }
+
+        if save {
+            editor.status = match asset_server.get_handle_path(&plane.descriptor) {
+                Some(path) => match Self::save(path.path(), &descriptor) {
+                    Ok(()) => format!("saved {}", path.path().display()),
+                    Err(err) => format!("error: {}", err),
+                },
+                None => String::from("error: descriptor has no path"),
+            };
+        }
+
+        if changed {
+            for surface in descriptor.surfaces.iter_mut() {
+                surface.polar_handle = surface
+                    .polar
+                    .as_ref()
+                    .map(|path| asset_server.load(path.as_str()));
+            }
+
+            descriptor.expand_surfaces();
+
+            if let Some(d) = descriptors.get_mut(&plane.descriptor) {
+                *d = descriptor;
+            }
+        }
+    }
+
+    fn descriptor_ui(&mut self, ui: &mut Ui, descriptor: &mut PlaneDescriptor) -> bool {
+        let mut context = Context::new_shared(None);
+        let mut changed = false;
+
+        egui::Grid::new("descriptor").show(ui, |ui| {
+            ui.label("mass");
+            changed |= descriptor.mass.ui(ui, Default::default(), &mut context);
+            ui.end_row();
+
+            ui.label("center_of_mass");
+            changed |= descriptor
+                .center_of_mass
+                .ui(ui, Default::default(), &mut context);
+            ui.end_row();
+        });
+
+        ui.separator();
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("groups");
+
+            for group in descriptor.group_names() {
+                let selected = self.group.as_ref() == Some(&group);
+
+                if ui.selectable_label(selected, &group).clicked() {
+                    self.group = if selected { None } else { Some(group) };
+                }
+            }
+        });
+
+        ui.separator();
+
+        let mut duplicate = None;
+        let mut remove = None;
+
+        for (i, surface) in descriptor.surfaces.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let name = if surface.name.is_empty() {
+                    format!("surface {}", i)
+                } else {
+                    surface.name.clone()
+                };
+
+                if ui
+                    .selectable_label(self.selected == Some(i), name)
+                    .clicked()
+                {
+                    self.selected = if self.selected == Some(i) {
+                        None
+                    } else {
+                        Some(i)
+                    };
+                }
+
+                if ui.small_button("duplicate").clicked() {
+                    duplicate = Some(i);
+                }
+
+                if ui.small_button("remove").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+
+        if ui.button("Add surface").clicked() {
+            descriptor.surfaces.push(PlaneSurface {
+                name: format!("surface {}", descriptor.surfaces.len()),
+                span: 1.0,
+                chord: 1.0,
+                ..Default::default()
+            });
+            self.selected = Some(descriptor.surfaces.len() - 1);
+            changed = true;
+        }
+
+        if let Some(i) = duplicate {
+            let mut surface = descriptor.surfaces[i].clone();
+            surface.name.push_str(" copy");
+            descriptor.surfaces.insert(i + 1, surface);
+            self.selected = Some(i + 1);
+            changed = true;
+        }
+
+        if let Some(i) = remove {
+            descriptor.surfaces.remove(i);
+            self.selected = None;
+            changed = true;
+        }
+
+        if let Some(surface) = self.selected.and_then(|i| descriptor.surfaces.get_mut(i)) {
+            ui.separator();
+
+            changed |= surface.ui(ui, Default::default(), &mut context);
+        }
+
+        ui.separator();
+
+        ui.collapsing("Bodies", |ui| {
+            changed |= descriptor.bodies.ui(ui, Default::default(), &mut context);
+        });
+
+        ui.collapsing("Engines", |ui| {
+            changed |= descriptor.engines.ui(ui, Default::default(), &mut context);
+        });
+
+        ui.collapsing("Fuel tanks", |ui| {
+            changed |= descriptor
+                .fuel_tanks
+                .ui(ui, Default::default(), &mut context);
+        });
+
+        ui.collapsing("Payload stations", |ui| {
+            changed |= descriptor
+                .payload_stations
+                .ui(ui, Default::default(), &mut context);
+        });
+
+        ui.collapsing("Floats", |ui| {
+            changed |= descriptor.floats.ui(ui, Default::default(), &mut context);
+        });
+
+        ui.collapsing("Seats", |ui| {
+            changed |= descriptor.seats.ui(ui, Default::default(), &mut context);
+        });
+
+        ui.collapsing("Doors", |ui| {
+            changed |= descriptor.doors.ui(ui, Default::default(), &mut context);
+        });
+
+        ui.collapsing("CG envelope", |ui| {
+            let mut enabled = descriptor.cg_envelope.is_some();
+
+            if ui.checkbox(&mut enabled, "enabled").changed() {
+                descriptor.cg_envelope = enabled.then(CgEnvelope::default);
+                changed = true;
+            }
+
+            if let Some(ref mut envelope) = descriptor.cg_envelope {
+                changed |= envelope.ui(ui, Default::default(), &mut context);
+            }
+        });
+
+        changed
+    }
+
+    /// Shows the loading of each payload station and fuel tank of the plane,
+    /// and the resulting mass and center of mass.
+    fn weight_and_balance_ui(ui: &mut Ui, descriptor: &PlaneDescriptor, plane: &mut Plane) {
+        egui::Grid::new("weight_and_balance").show(ui, |ui| {
+            for (station, payload) in descriptor
+                .payload_stations
+                .iter()
+                .zip(plane.payload.iter_mut())
+            {
+                ui.label(&station.name);
+                ui.add(egui::Slider::new(payload, 0.0..=station.max_mass).suffix(" kg"));
+                ui.end_row();
+            }
+
+            for (tank, fuel) in descriptor.fuel_tanks.iter().zip(plane.fuel.iter_mut()) {
+                ui.label(&tank.name);
+                ui.add(egui::Slider::new(fuel, 0.0..=tank.capacity).suffix(" kg"));
+                ui.end_row();
+            }
+        });
+
+        let weight_and_balance = descriptor.weight_and_balance(&plane.fuel, &plane.payload);
+        let mass_properties = weight_and_balance.mass_properties;
+
+        ui.label(format!("total mass: {:.1} kg", mass_properties.mass));
+        ui.label(format!(
+            "center of mass: ({:.2}, {:.2}, {:.2})",
+            mass_properties.center_of_mass.x,
+            mass_properties.center_of_mass.y,
+            mass_properties.center_of_mass.z,
+        ));
+
+        if let Some(ref envelope) = descriptor.cg_envelope {
+            ui.label(format!(
+                "envelope: z {:.2} to {:.2}, max {:.1} kg",
+                envelope.aft_limit, envelope.forward_limit, envelope.max_mass,
+            ));
+        }
+
+        if !weight_and_balance.mass_ok {
+            ui.colored_label(egui::Color32::RED, "over maximum mass");
+        }
+
+        if !weight_and_balance.cg_ok {
+            ui.colored_label(egui::Color32::RED, "center of mass outside envelope");
+        }
+
+        if weight_and_balance.is_ok() {
+            ui.colored_label(egui::Color32::GREEN, "within limits");
+        }
+    }
+
+    /// Writes the descriptor back to its `.plane.ron` file.
+    ///
+    /// Descriptors that were loaded from a `base` only write what differs
+    /// from it.
+    fn save(path: &Path, descriptor: &PlaneDescriptor) -> Result<(), anyhow::Error> {
+        let file = PlaneDescriptorFile::from_descriptor(descriptor)?;
+        let config = PrettyConfig::new().extensions(Extensions::IMPLICIT_SOME);
+        let ron = ron::ser::to_string_pretty(&file, config)?;
+        std::fs::write(Path::new("assets").join(path), ron)?;
+
+        Ok(())
+    }
+}
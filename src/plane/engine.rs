@@ -0,0 +1,242 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use serde::{Deserialize, Serialize};
+
+use super::SurfaceForces;
+
+/// Speed of sound used to compute the Mach number, in m/s.
+const SPEED_OF_SOUND: f32 = 340.0;
+
+#[derive(Inspectable, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EngineKind {
+    /// Thrust proportional to throttle, with mixture control.
+    Simple,
+    /// Thrust driven by the spool speed N1, which lags behind the throttle.
+    Turbine,
+}
+
+impl Default for EngineKind {
+    fn default() -> Self {
+        Self::Simple
+    }
+}
+
+fn default_thrust_axis() -> Vec3 {
+    Vec3::Z
+}
+
+const fn default_idle_n1() -> f32 {
+    0.25
+}
+
+const fn default_spool_up_time() -> f32 {
+    4.0
+}
+
+const fn default_spool_down_time() -> f32 {
+    3.0
+}
+
+#[derive(Inspectable, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlaneEngine {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub kind: EngineKind,
+    pub position: Vec3,
+    /// Direction of thrust in plane space.
+    #[serde(default = "default_thrust_axis")]
+    pub thrust_axis: Vec3,
+    /// Thrust at full throttle and sea level, in newtons.
+    pub max_thrust: f32,
+    /// Fuel flow at full throttle, in kg/s.
+    #[serde(default)]
+    pub fuel_flow: f32,
+    /// Fuel flow of a turbine at idle, in kg/s.
+    #[serde(default)]
+    pub idle_fuel_flow: f32,
+    /// Spool speed of a turbine at idle, as a fraction of full N1.
+    #[serde(default = "default_idle_n1")]
+    pub idle_n1: f32,
+    /// Time constant of a turbine spooling up, in seconds.
+    #[serde(default = "default_spool_up_time")]
+    pub spool_up_time: f32,
+    /// Time constant of a turbine spooling down, in seconds.
+    #[serde(default = "default_spool_down_time")]
+    pub spool_down_time: f32,
+    /// Thrust added by the afterburner at sea level, in newtons.
+    #[serde(default)]
+    pub afterburner_thrust: f32,
+    /// Fuel flow added by the afterburner, in kg/s.
+    #[serde(default)]
+    pub afterburner_fuel_flow: f32,
+}
+
+impl Default for PlaneEngine {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            kind: EngineKind::Simple,
+            position: Vec3::ZERO,
+            thrust_axis: default_thrust_axis(),
+            max_thrust: 0.0,
+            fuel_flow: 0.0,
+            idle_fuel_flow: 0.0,
+            idle_n1: default_idle_n1(),
+            spool_up_time: default_spool_up_time(),
+            spool_down_time: default_spool_down_time(),
+            afterburner_thrust: 0.0,
+            afterburner_fuel_flow: 0.0,
+        }
+    }
+}
+
+impl PlaneEngine {
+    pub fn calculate_forces(
+        &self,
+        thrust: f32,
+        relative_position: Vec3,
+        rotation: Quat,
+    ) -> SurfaceForces {
+        let linear = rotation * self.thrust_axis.normalize_or_zero() * thrust;
+        let angular = Vec3::cross(relative_position, linear);
+
+        SurfaceForces { linear, angular }
+    }
+}
+
+/// Runtime state of a single engine of a [`Plane`](super::Plane).
+#[derive(Clone, Debug)]
+pub struct EngineState {
+    pub throttle: f32,
+    pub mixture: f32,
+    pub running: bool,
+    /// Set while the selected fuel tanks are empty.
+    pub starved: bool,
+    /// Set while the engine has failed, it can't be restarted until repaired.
+    pub failed: bool,
+    /// Fraction of the normal thrust the engine produces, lowered when it
+    /// runs rough.
+    pub power: f32,
+    pub afterburner: bool,
+    /// Whether throttle, mixture and ignition input applies to the engine.
+    pub selected: bool,
+    /// Spool speed of a turbine, as a fraction of full N1.
+    pub n1: f32,
+    /// Thrust produced in the last update, in newtons.
+    pub thrust: f32,
+    /// Fuel burned in the last update, in kg/s.
+    pub fuel_flow: f32,
+}
+
+impl Default for EngineState {
+    fn default() -> Self {
+        Self {
+            throttle: 0.0,
+            mixture: 1.0,
+            running: true,
+            starved: false,
+            failed: false,
+            power: 1.0,
+            afterburner: false,
+            selected: true,
+            n1: 0.0,
+            thrust: 0.0,
+            fuel_flow: 0.0,
+        }
+    }
+}
+
+impl EngineState {
+    /// Updates and returns the thrust of the engine.
+    pub fn update(
+        &mut self,
+        engine: &PlaneEngine,
+        air_density: f32,
+        airspeed: f32,
+        delta_seconds: f32,
+    ) -> f32 {
+        match engine.kind {
+            EngineKind::Simple => self.update_simple(engine, air_density),
+            EngineKind::Turbine => self.update_turbine(
+                engine,
+                air_density,
+                airspeed / SPEED_OF_SOUND,
+                delta_seconds,
+            ),
+        }
+
+        self.thrust *= self.power;
+        self.thrust
+    }
+
+    /// Power is best when the mixture matches the relative air density, so
+    /// the mixture has to be leaned at altitude. A mixture of zero cuts the
+    /// engine.
+    fn update_simple(&mut self, engine: &PlaneEngine, air_density: f32) {
+        if !self.running || self.starved || self.failed || self.mixture <= 0.0 {
+            self.thrust = 0.0;
+            self.fuel_flow = 0.0;
+            return;
+        }
+
+        let mixture_error = self.mixture - air_density;
+        let mixture_power = f32::max(1.0 - 2.0 * mixture_error * mixture_error, 0.0);
+
+        self.thrust = engine.max_thrust * self.throttle * air_density * mixture_power;
+        self.fuel_flow = engine.fuel_flow * self.throttle * self.mixture;
+    }
+
+    /// N1 follows the throttle with separate spool up and down time
+    /// constants. Thrust grows with the cube of N1, falls off with density and
+    /// dips before ram recovery with Mach. The afterburner only lights at
+    /// full throttle with the spool near full speed.
+    fn update_turbine(
+        &mut self,
+        engine: &PlaneEngine,
+        air_density: f32,
+        mach: f32,
+        delta_seconds: f32,
+    ) {
+        let running = self.running && !self.starved && !self.failed;
+
+        // an idle at full N1 would leave no range to scale fuel flow over
+        let idle_n1 = engine.idle_n1.clamp(0.0, 0.99);
+
+        let target_n1 = if running {
+            idle_n1 + (1.0 - idle_n1) * self.throttle
+        } else {
+            0.0
+        };
+
+        let spool_time = if target_n1 > self.n1 {
+            engine.spool_up_time
+        } else {
+            engine.spool_down_time
+        };
+
+        // a time constant of 0 spools instantly, without dividing 0 by 0 on
+        // a paused frame
+        let spool_time = spool_time.max(1e-3);
+
+        self.n1 += (target_n1 - self.n1) * (1.0 - f32::exp(-delta_seconds / spool_time));
+
+        if !running {
+            self.thrust = 0.0;
+            self.fuel_flow = 0.0;
+            return;
+        }
+
+        let ram = 1.0 - 0.25 * mach + 0.3 * mach * mach;
+        let density = air_density.powf(0.7);
+        let core = ((self.n1 - idle_n1) / (1.0 - idle_n1)).clamp(0.0, 1.0);
+
+        self.thrust = engine.max_thrust * self.n1.powi(3) * density * ram;
+        self.fuel_flow = engine.idle_fuel_flow + (engine.fuel_flow - engine.idle_fuel_flow) * core;
+
+        if self.afterburner && self.throttle >= 1.0 && self.n1 > 0.95 {
+            self.thrust += engine.afterburner_thrust * density * ram;
+            self.fuel_flow += engine.afterburner_fuel_flow;
+        }
+    }
+}
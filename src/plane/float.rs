@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use bevy_prototype_debug_lines::DebugLines;
+use serde::{Deserialize, Serialize};
+
+use super::SurfaceForces;
+
+/// A float or hull giving buoyancy on water, treated as a box aligned with
+/// the plane.
+#[derive(Inspectable, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlaneFloat {
+    #[serde(default)]
+    pub name: String,
+    /// Center of the box.
+    pub position: Vec3,
+    pub size: Vec3,
+    /// Drag coefficient for water flowing along the float.
+    pub drag_coefficient: f32,
+    /// Drag coefficient for water flowing against the side or bottom.
+    pub side_drag_coefficient: f32,
+}
+
+impl Default for PlaneFloat {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            position: Vec3::ZERO,
+            size: Vec3::ONE,
+            drag_coefficient: 0.1,
+            side_drag_coefficient: 1.0,
+        }
+    }
+}
+
+impl PlaneFloat {
+    /// Returns the fraction of the float below `water_level`.
+    pub fn submerged_fraction(&self, position: Vec3, water_level: f32) -> f32 {
+        if self.size.y <= 0.0 {
+            return 0.0;
+        }
+
+        let bottom = position.y - self.size.y / 2.0;
+
+        f32::clamp((water_level - bottom) / self.size.y, 0.0, 1.0)
+    }
+
+    /// Computes buoyancy and hydrodynamic drag, `water_velocity` is the
+    /// velocity of the water relative to the float and `buoyancy` scales the
+    /// displaced volume, lowered when the float is flooded.
+    ///
+    /// Drag is limited to `max_drag` so it can't reverse the flow within a
+    /// single step.
+    pub fn calculate_forces(
+        &self,
+        water_velocity: Vec3,
+        water_level: f32,
+        water_density: f32,
+        buoyancy: f32,
+        max_drag: f32,
+        relative_position: Vec3,
+        position: Vec3,
+        rotation: Quat,
+        lines: &mut DebugLines,
+    ) -> SurfaceForces {
+        let fraction = self.submerged_fraction(position, water_level);
+
+        if fraction <= 0.0 {
+            return SurfaceForces::default();
+        }
+
+        let volume = self.size.x * self.size.y * self.size.z * fraction;
+        let lift = Vec3::Y * water_density * 9.81 * volume * buoyancy;
+
+        let local_velocity = rotation.conjugate() * water_velocity;
+        let speed = local_velocity.length();
+
+        let frontal_area = self.size.x * self.size.y * fraction;
+        let side_area = self.size.z * self.size.y * fraction;
+        let bottom_area = self.size.x * self.size.z;
+
+        let drag = Vec3::new(
+            self.side_drag_coefficient * side_area * local_velocity.x,
+            self.side_drag_coefficient * bottom_area * local_velocity.y,
+            self.drag_coefficient * frontal_area * local_velocity.z,
+        ) * 0.5
+            * water_density
+            * speed;
+
+        let drag = (rotation * drag).clamp_length_max(max_drag);
+        let linear = lift + drag;
+
+        if cfg!(feature = "debug") {
+            lines.line_colored(position, position + linear * 0.001, 0.0, Color::CYAN);
+        }
+
+        let angular = Vec3::cross(relative_position, linear);
+
+        SurfaceForces { linear, angular }
+    }
+}
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use serde::{Deserialize, Serialize};
+
+const fn default_initial_fraction() -> f32 {
+    1.0
+}
+
+#[derive(Inspectable, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FuelTank {
+    #[serde(default)]
+    pub name: String,
+    pub position: Vec3,
+    /// Capacity of the tank, in kg.
+    pub capacity: f32,
+    /// Fraction of the capacity the tank starts with.
+    #[serde(default = "default_initial_fraction")]
+    pub initial_fraction: f32,
+}
+
+impl Default for FuelTank {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            position: Vec3::ZERO,
+            capacity: 0.0,
+            initial_fraction: default_initial_fraction(),
+        }
+    }
+}
+
+impl FuelTank {
+    pub fn initial_fuel(&self) -> f32 {
+        self.capacity * self.initial_fraction.clamp(0.0, 1.0)
+    }
+}
+
+/// Which tanks feed the engines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FuelSelector {
+    All,
+    Tank(usize),
+    Off,
+}
+
+impl Default for FuelSelector {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+impl FuelSelector {
+    /// Returns the next position of the selector, cycling through all tanks
+    /// and off.
+    pub fn next(self, tanks: usize) -> Self {
+        match self {
+            Self::All if tanks > 0 => Self::Tank(0),
+            Self::All => Self::Off,
+            Self::Tank(i) if i + 1 < tanks => Self::Tank(i + 1),
+            Self::Tank(_) => Self::Off,
+            Self::Off => Self::All,
+        }
+    }
+
+    /// Returns whether any selected tank has fuel left.
+    pub fn has_fuel(&self, fuel: &[f32]) -> bool {
+        match *self {
+            Self::All => fuel.iter().any(|fuel| *fuel > 0.0),
+            Self::Tank(i) => fuel.get(i).map_or(false, |fuel| *fuel > 0.0),
+            Self::Off => false,
+        }
+    }
+
+    /// Draws `amount` kg of fuel from the selected tanks, spread evenly over
+    /// the tanks that aren't empty.
+    pub fn draw(&self, fuel: &mut [f32], mut amount: f32) {
+        match *self {
+            Self::All => {
+                for _ in 0..fuel.len() {
+                    let tanks = fuel.iter().filter(|fuel| **fuel > 0.0).count();
+
+                    if tanks == 0 || amount <= 0.0 {
+                        break;
+                    }
+
+                    let share = amount / tanks as f32;
+
+                    for fuel in fuel.iter_mut().filter(|fuel| **fuel > 0.0) {
+                        let drawn = f32::min(share, *fuel);
+                        *fuel -= drawn;
+                        amount -= drawn;
+                    }
+                }
+            }
+            Self::Tank(i) => {
+                if let Some(fuel) = fuel.get_mut(i) {
+                    *fuel = f32::max(*fuel - amount, 0.0);
+                }
+            }
+            Self::Off => {}
+        }
+    }
+}
@@ -0,0 +1,540 @@
+use bevy::{
+    asset::{AssetLoader, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::HashMap,
+};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContext,
+    egui::{self, Ui},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::controls::{Action, Controls};
+
+use super::{EngineState, Plane, PlaneDescriptor, PlaneSurface, SurfaceInputKind};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Malfunction {
+    /// The engine stops and can't be restarted.
+    EngineOut { engine: usize },
+    /// Thrust randomly drops by up to `severity`, a fraction of full thrust.
+    RoughRunning { engine: usize, severity: f32 },
+    /// Surfaces with this name or group stay where they were when they jammed.
+    SurfaceJammed { surface: String },
+    /// Surfaces with this name or group no longer follow the controls and
+    /// trail in the airflow.
+    SurfaceDisconnected { surface: String },
+    /// Flaps stay where they were when they got stuck.
+    FlapsStuck,
+    /// The tank loses fuel at `rate` kg/s.
+    FuelLeak { tank: usize, rate: f32 },
+}
+
+impl Malfunction {
+    pub fn description(&self) -> String {
+        match self {
+            Self::EngineOut { engine } => format!("engine {} out", engine + 1),
+            Self::RoughRunning { engine, severity } => format!(
+                "engine {} running rough ({:.0}%)",
+                engine + 1,
+                severity * 100.0
+            ),
+            Self::SurfaceJammed { surface } => format!("{} jammed", surface),
+            Self::SurfaceDisconnected { surface } => format!("{} disconnected", surface),
+            Self::FlapsStuck => String::from("flaps stuck"),
+            Self::FuelLeak { tank, rate } => {
+                format!("tank {} leaking {:.3} kg/s", tank + 1, rate)
+            }
+        }
+    }
+
+    /// Returns whether the malfunction freezes or frees `surface`.
+    fn affects_surface(&self, surface: &PlaneSurface) -> bool {
+        match self {
+            Self::SurfaceJammed { surface: name } | Self::SurfaceDisconnected { surface: name } => {
+                surface.name == *name || surface.in_group(name)
+            }
+            Self::FlapsStuck => surface.input_kind == SurfaceInputKind::Flap,
+            _ => false,
+        }
+    }
+}
+
+/// When a scheduled malfunction triggers, counted from when the scenario is
+/// applied.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum MalfunctionTrigger {
+    Immediate,
+    /// After a fixed number of seconds.
+    After(f32),
+    /// At a random time within the given number of seconds.
+    Within(f32),
+}
+
+impl Default for MalfunctionTrigger {
+    fn default() -> Self {
+        Self::Immediate
+    }
+}
+
+impl MalfunctionTrigger {
+    fn time(&self) -> f32 {
+        match *self {
+            Self::Immediate => 0.0,
+            Self::After(seconds) => seconds,
+            Self::Within(seconds) => rand::thread_rng().gen_range(0.0..=f32::max(seconds, 0.0)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduledMalfunction {
+    pub malfunction: Malfunction,
+    #[serde(default)]
+    pub trigger: MalfunctionTrigger,
+}
+
+/// A training scenario, loaded from a `.scenario.ron` file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "6d1f3c2a-8e57-4b0d-a3f9-52c7e18b4d90"]
+pub struct Scenario {
+    pub malfunctions: Vec<ScheduledMalfunction>,
+}
+
+pub struct ScenarioAssetLoader;
+
+impl AssetLoader for ScenarioAssetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::asset::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async {
+            let scenario = ron::de::from_bytes::<Scenario>(bytes)?;
+
+            load_context.set_default_asset(LoadedAsset::new(scenario));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["scenario.ron"]
+    }
+}
+
+/// Pending and active malfunctions of a [`Plane`].
+#[derive(Clone, Debug, Default)]
+pub struct Malfunctions {
+    /// Seconds since the scenario was applied.
+    pub elapsed: f32,
+    /// Malfunctions waiting to trigger, with their trigger time.
+    pub pending: Vec<(f32, Malfunction)>,
+    pub active: Vec<Malfunction>,
+    /// Flap angle and deployment of frozen surfaces, by index into the
+    /// expanded surfaces.
+    frozen: HashMap<usize, (f32, f32)>,
+    /// Current and target power loss of rough running engines, by engine
+    /// index, as a fraction of `severity`.
+    roughness: HashMap<usize, (f32, f32)>,
+    /// Seconds until rough running engines pick a new target power loss.
+    rough_timer: f32,
+}
+
+/// Seconds between changes of the power loss of a rough running engine.
+const ROUGH_INTERVAL: f32 = 0.4;
+/// Time constant of the power loss following its target, in seconds.
+const ROUGH_RESPONSE: f32 = 0.15;
+
+impl Malfunctions {
+    /// Replaces all pending and active malfunctions with those of `scenario`.
+    pub fn schedule(&mut self, scenario: &Scenario) {
+        self.clear();
+
+        for scheduled in scenario.malfunctions.iter() {
+            self.pending
+                .push((scheduled.trigger.time(), scheduled.malfunction.clone()));
+        }
+    }
+
+    pub fn trigger(&mut self, malfunction: Malfunction) {
+        if !self.active.contains(&malfunction) {
+            self.active.push(malfunction);
+        }
+    }
+
+    /// Repairs everything and drops pending malfunctions.
+    pub fn clear(&mut self) {
+        self.elapsed = 0.0;
+        self.pending.clear();
+        self.active.clear();
+        self.frozen.clear();
+        self.roughness.clear();
+    }
+
+    /// Advances the scenario clock and triggers due malfunctions.
+    pub fn update(&mut self, delta_seconds: f32) {
+        self.elapsed += delta_seconds;
+
+        let elapsed = self.elapsed;
+        let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|(time, _)| *time <= elapsed);
+        self.pending = pending;
+
+        for (_, malfunction) in due {
+            self.trigger(malfunction);
+        }
+
+        // the power loss of rough running engines surges towards a new random
+        // target at a fixed interval, so it doesn't depend on the frame rate
+        let mut rng = rand::thread_rng();
+
+        self.rough_timer -= delta_seconds;
+        let reroll = self.rough_timer <= 0.0;
+
+        if reroll {
+            self.rough_timer = ROUGH_INTERVAL;
+        }
+
+        let response = 1.0 - f32::exp(-delta_seconds / ROUGH_RESPONSE);
+
+        for malfunction in self.active.iter() {
+            if let Malfunction::RoughRunning { engine, .. } = *malfunction {
+                let (value, target) = self.roughness.entry(engine).or_default();
+
+                if reroll {
+                    *target = rng.gen::<f32>();
+                }
+
+                *value += (*target - *value) * response;
+            }
+        }
+    }
+
+    /// Returns the flap angle and deployment `surface` actually reaches, given
+    /// those commanded by the controls.
+    pub fn surface_controls(
+        &mut self,
+        index: usize,
+        surface: &PlaneSurface,
+        flap_angle: f32,
+        deployment: f32,
+    ) -> (f32, f32) {
+        let mut controls = (flap_angle, deployment);
+
+        for malfunction in self.active.iter() {
+            if !malfunction.affects_surface(surface) {
+                continue;
+            }
+
+            controls = match malfunction {
+                Malfunction::SurfaceDisconnected { .. } => (0.0, 0.0),
+                _ => *self.frozen.entry(index).or_insert(controls),
+            };
+        }
+
+        controls
+    }
+
+    /// Fails engines and sets the power of rough running ones.
+    pub fn apply_engines(&self, engines: &mut [EngineState]) {
+        for engine in engines.iter_mut() {
+            engine.failed = false;
+            engine.power = 1.0;
+        }
+
+        for malfunction in self.active.iter() {
+            match *malfunction {
+                Malfunction::EngineOut { engine } => {
+                    if let Some(engine) = engines.get_mut(engine) {
+                        engine.failed = true;
+                    }
+                }
+                Malfunction::RoughRunning { engine, severity } => {
+                    let roughness = self.roughness.get(&engine).map_or(0.0, |(value, _)| *value);
+
+                    if let Some(engine) = engines.get_mut(engine) {
+                        engine.power *= 1.0 - severity.clamp(0.0, 1.0) * roughness;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Drains leaking tanks.
+    pub fn leak(&self, fuel: &mut [f32], delta_seconds: f32) {
+        for malfunction in self.active.iter() {
+            if let Malfunction::FuelLeak { tank, rate } = *malfunction {
+                if let Some(fuel) = fuel.get_mut(tank) {
+                    *fuel = f32::max(*fuel - rate * delta_seconds, 0.0);
+                }
+            }
+        }
+    }
+
+    /// Picks a random malfunction that applies to `descriptor`.
+    pub fn random(descriptor: &PlaneDescriptor) -> Option<Malfunction> {
+        let mut rng = rand::thread_rng();
+        let mut candidates = Vec::new();
+
+        for engine in 0..descriptor.engines.len() {
+            candidates.push(Malfunction::EngineOut { engine });
+            candidates.push(Malfunction::RoughRunning {
+                engine,
+                severity: rng.gen_range(0.2..0.8),
+            });
+        }
+
+        for surface in descriptor.expanded_surfaces() {
+            if !surface.name.is_empty() {
+                candidates.push(Malfunction::SurfaceJammed {
+                    surface: surface.name.clone(),
+                });
+                candidates.push(Malfunction::SurfaceDisconnected {
+                    surface: surface.name.clone(),
+                });
+            }
+        }
+
+        if descriptor
+            .surfaces
+            .iter()
+            .any(|surface| surface.input_kind == SurfaceInputKind::Flap)
+        {
+            candidates.push(Malfunction::FlapsStuck);
+        }
+
+        for tank in 0..descriptor.fuel_tanks.len() {
+            candidates.push(Malfunction::FuelLeak {
+                tank,
+                rate: rng.gen_range(0.01..0.1),
+            });
+        }
+
+        if candidates.is_empty() {
+            None
+        } else {
+            Some(candidates.swap_remove(rng.gen_range(0..candidates.len())))
+        }
+    }
+}
+
+/// Debug menu for triggering malfunctions on the active plane, toggled with
+/// F2.
+#[derive(Clone, Debug)]
+pub struct MalfunctionMenu {
+    pub open: bool,
+    pub scenario_path: String,
+    pub scenario: Option<Handle<Scenario>>,
+    pub engine: usize,
+    pub severity: f32,
+    pub surface: String,
+    pub tank: usize,
+    pub leak_rate: f32,
+}
+
+impl Default for MalfunctionMenu {
+    fn default() -> Self {
+        Self {
+            open: false,
+            scenario_path: String::from("scenarios/engine_failure.scenario.ron"),
+            scenario: None,
+            engine: 0,
+            severity: 0.5,
+            surface: String::new(),
+            tank: 0,
+            leak_rate: 0.05,
+        }
+    }
+}
+
+impl MalfunctionMenu {
+    pub fn toggle_system(
+        controls: Res<Controls>,
+        mut windows: ResMut<Windows>,
+        mut menu: ResMut<MalfunctionMenu>,
+    ) {
+        if controls.just_pressed(Action::ToggleMalfunctions) {
+            menu.open = !menu.open;
+
+            if menu.open {
+                let window = windows.primary_mut();
+                window.set_cursor_lock_mode(false);
+                window.set_cursor_visibility(true);
+            }
+        }
+    }
+
+    pub fn system(
+        mut egui_context: ResMut<EguiContext>,
+        mut menu: ResMut<MalfunctionMenu>,
+        asset_server: Res<AssetServer>,
+        scenarios: Res<Assets<Scenario>>,
+        descriptors: Res<Assets<PlaneDescriptor>>,
+        mut query: Query<(Entity, &mut Plane)>,
+    ) {
+        let active = query
+            .iter()
+            .find(|(_, plane)| plane.entered)
+            .or_else(|| query.iter().next())
+            .map(|(entity, _)| entity);
+
+        let mut plane = match active.and_then(|entity| query.get_mut(entity).ok()) {
+            Some((_, plane)) => plane,
+            None => return,
+        };
+
+        // scenarios are applied once they finish loading
+        if let Some(scenario) = menu.scenario.as_ref().and_then(|s| scenarios.get(s)) {
+            plane.malfunctions.schedule(scenario);
+            menu.scenario = None;
+        }
+
+        if !menu.open {
+            return;
+        }
+
+        let descriptor = if let Some(d) = descriptors.get(&plane.descriptor) {
+            d
+        } else {
+            return;
+        };
+
+        let mut open = menu.open;
+
+        egui::Window::new("Malfunctions")
+            .open(&mut open)
+            .default_width(280.0)
+            .show(egui_context.ctx_mut(), |ui| {
+                menu.ui(ui, &asset_server, descriptor, &mut plane);
+            });
+
+        menu.open = open;
+    }
+
+    fn ui(
+        &mut self,
+        ui: &mut Ui,
+        asset_server: &AssetServer,
+        descriptor: &PlaneDescriptor,
+        plane: &mut Plane,
+    ) {
+        let malfunctions = &mut plane.malfunctions;
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.scenario_path);
+
+            if ui.button("Load").clicked() {
+                self.scenario = Some(asset_server.load(self.scenario_path.as_str()));
+            }
+        });
+
+        ui.separator();
+
+        ui.label(format!("elapsed: {:.0} s", malfunctions.elapsed));
+        ui.label(format!("damage: {:.0}%", plane.damage * 100.0));
+
+        for malfunction in malfunctions.active.iter() {
+            ui.colored_label(egui::Color32::RED, malfunction.description());
+        }
+
+        for (time, malfunction) in malfunctions.pending.iter() {
+            ui.label(format!("{:.0} s: {}", time, malfunction.description()));
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Random").clicked() {
+                if let Some(malfunction) = Malfunctions::random(descriptor) {
+                    malfunctions.trigger(malfunction);
+                }
+            }
+
+            if ui.button("Repair all").clicked() {
+                malfunctions.clear();
+                plane.damage = 0.0;
+            }
+        });
+
+        ui.separator();
+
+        egui::Grid::new("malfunctions").show(ui, |ui| {
+            let engines = descriptor.engines.len().max(1) - 1;
+            let tanks = descriptor.fuel_tanks.len().max(1) - 1;
+
+            ui.label("engine");
+            ui.add(egui::DragValue::new(&mut self.engine).clamp_range(0..=engines));
+            ui.end_row();
+
+            ui.label("severity");
+            ui.add(egui::Slider::new(&mut self.severity, 0.0..=1.0));
+            ui.end_row();
+
+            ui.label("surface or group");
+            egui::ComboBox::from_id_source("malfunction_surface")
+                .selected_text(self.surface.as_str())
+                .show_ui(ui, |ui| {
+                    for group in descriptor.group_names() {
+                        ui.selectable_value(&mut self.surface, group.clone(), group);
+                    }
+
+                    for surface in descriptor.expanded_surfaces() {
+                        if !surface.name.is_empty() {
+                            let name = surface.name.clone();
+                            ui.selectable_value(&mut self.surface, name.clone(), name);
+                        }
+                    }
+                });
+            ui.end_row();
+
+            ui.label("tank");
+            ui.add(egui::DragValue::new(&mut self.tank).clamp_range(0..=tanks));
+            ui.end_row();
+
+            ui.label("leak rate");
+            ui.add(egui::Slider::new(&mut self.leak_rate, 0.0..=0.5).suffix(" kg/s"));
+            ui.end_row();
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Engine out").clicked() {
+                malfunctions.trigger(Malfunction::EngineOut {
+                    engine: self.engine,
+                });
+            }
+
+            if ui.button("Rough running").clicked() {
+                malfunctions.trigger(Malfunction::RoughRunning {
+                    engine: self.engine,
+                    severity: self.severity,
+                });
+            }
+
+            if ui.button("Jam surface").clicked() && !self.surface.is_empty() {
+                malfunctions.trigger(Malfunction::SurfaceJammed {
+                    surface: self.surface.clone(),
+                });
+            }
+
+            if ui.button("Disconnect surface").clicked() && !self.surface.is_empty() {
+                malfunctions.trigger(Malfunction::SurfaceDisconnected {
+                    surface: self.surface.clone(),
+                });
+            }
+
+            if ui.button("Stick flaps").clicked() {
+                malfunctions.trigger(Malfunction::FlapsStuck);
+            }
+
+            if ui.button("Fuel leak").clicked() {
+                malfunctions.trigger(Malfunction::FuelLeak {
+                    tank: self.tank,
+                    rate: self.leak_rate,
+                });
+            }
+        });
+    }
+}
@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+
+/// Total mass and center of mass, in plane space.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MassProperties {
+    pub mass: f32,
+    pub center_of_mass: Vec3,
+}
+
+impl MassProperties {
+    pub fn new(mass: f32, center_of_mass: Vec3) -> Self {
+        Self {
+            mass,
+            center_of_mass,
+        }
+    }
+
+    /// Adds a point mass at `position`.
+    pub fn add(&mut self, mass: f32, position: Vec3) {
+        let total = self.mass + mass;
+
+        if total > 0.0 {
+            self.center_of_mass = (self.center_of_mass * self.mass + position * mass) / total;
+        }
+
+        self.mass = total;
+    }
+}
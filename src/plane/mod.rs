@@ -1,9 +1,33 @@
 mod asset;
+mod autopilot;
+mod body;
 mod camera;
+mod editor;
+mod engine;
+mod float;
+mod fuel;
+mod malfunction;
+mod mass;
+mod mouse_aim;
+mod payload;
 mod plane;
+mod polar;
+mod seat;
 mod surface;
 
 pub use asset::*;
+pub use autopilot::*;
+pub use body::*;
 pub use camera::*;
+pub use editor::*;
+pub use engine::*;
+pub use float::*;
+pub use fuel::*;
+pub use malfunction::*;
+pub use mass::*;
+pub use mouse_aim::*;
+pub use payload::*;
 pub use plane::*;
+pub use polar::*;
+pub use seat::*;
 pub use surface::*;
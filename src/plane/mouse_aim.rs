@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::DebugLines;
+
+use crate::controls::{Action, Controls};
+
+use super::{Plane, SurfaceInputState};
+
+/// Steers a plane towards a direction set with the mouse, like an instructor
+/// flying for the player.
+#[derive(Component, Clone, Debug)]
+pub struct MouseAim {
+    pub enabled: bool,
+    /// Direction the plane is steered towards, in world space.
+    pub direction: Vec3,
+    /// Pitch input per radian between the nose and the aim.
+    pub pitch_gain: f32,
+    pub yaw_gain: f32,
+    pub roll_gain: f32,
+    /// Input against each rad/s the plane turns at, keeping it from
+    /// overshooting the aim.
+    pub damping: f32,
+    /// Angle between the nose and the aim beyond which the plane banks
+    /// towards it, closer it levels the wings, in degrees.
+    pub bank_angle: f32,
+}
+
+impl Default for MouseAim {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            direction: Vec3::Z,
+            pitch_gain: 4.0,
+            yaw_gain: 2.0,
+            roll_gain: 3.0,
+            damping: 0.5,
+            bank_angle: 10.0,
+        }
+    }
+}
+
+/// Steepest the aim can point up or down, in degrees.
+const MAX_ELEVATION: f32 = 80.0;
+
+/// Distance of the drawn aim point from the plane.
+const AIM_DISTANCE: f32 = 200.0;
+
+impl MouseAim {
+    /// Returns the input that turns the plane towards the aim.
+    pub fn steer(&self, transform: &GlobalTransform, angular_velocity: Vec3) -> SurfaceInputState {
+        let forward = transform.local_z();
+        let left = transform.local_x();
+        let up = transform.local_y();
+
+        // the aim in plane space, +X left, +Y up and +Z forward
+        let aim = transform.rotation.inverse() * self.direction;
+
+        let nose_up_error = f32::atan2(aim.y, aim.z);
+        let yaw_left_error = f32::atan2(aim.x, aim.z);
+
+        // far off the nose the plane banks to put the aim above it, close to
+        // it the wings are leveled and the rudder finishes the turn
+        let bank_error = f32::atan2(aim.x, aim.y);
+        let level_error = left.y.clamp(-1.0, 1.0).asin();
+
+        let off_nose = forward.angle_between(self.direction);
+        let blend = (off_nose / self.bank_angle.to_radians()).clamp(0.0, 1.0);
+        let roll_left_error = level_error + (bank_error - level_error) * blend;
+
+        let nose_up_rate = -angular_velocity.dot(left);
+        let yaw_left_rate = angular_velocity.dot(up);
+        let roll_left_rate = -angular_velocity.dot(forward);
+
+        // positive pitch input lowers the nose, positive roll and yaw turn
+        // to the left
+        SurfaceInputState {
+            pitch: -(nose_up_error * self.pitch_gain - nose_up_rate * self.damping)
+                .clamp(-1.0, 1.0),
+            yaw: (yaw_left_error * self.yaw_gain - yaw_left_rate * self.damping).clamp(-1.0, 1.0),
+            roll: (roll_left_error * self.roll_gain - roll_left_rate * self.damping)
+                .clamp(-1.0, 1.0),
+            ..Default::default()
+        }
+    }
+
+    /// Toggles mouse aim in the controlled plane and moves the aim with the
+    /// mouse.
+    pub fn system(
+        controls: Res<Controls>,
+        mut lines: ResMut<DebugLines>,
+        mut query: Query<(&Plane, &mut MouseAim, &GlobalTransform)>,
+    ) {
+        for (plane, mut mouse_aim, transform) in query.iter_mut() {
+            // mouse aim is released with the controls, so it isn't still on
+            // when the player takes them again
+            if !plane.has_control() {
+                if mouse_aim.enabled {
+                    mouse_aim.enabled = false;
+                }
+
+                continue;
+            }
+
+            if controls.just_pressed(Action::ToggleMouseAim) {
+                mouse_aim.enabled = !mouse_aim.enabled;
+                mouse_aim.direction = transform.local_z();
+            }
+
+            if !mouse_aim.enabled {
+                continue;
+            }
+
+            let delta = controls.mouse_delta;
+            let direction = mouse_aim.direction;
+            let right = direction.cross(Vec3::Y).normalize_or_zero();
+
+            // moving the mouse right turns the aim right, up raises it
+            let turned = Quat::from_rotation_y(-delta.x) * direction;
+            let raised = Quat::from_axis_angle(right, -delta.y) * turned;
+
+            if raised.y.abs() < MAX_ELEVATION.to_radians().sin() {
+                mouse_aim.direction = raised.normalize();
+            } else {
+                mouse_aim.direction = turned.normalize();
+            }
+
+            let position = transform.translation;
+            let aim_point = position + mouse_aim.direction * AIM_DISTANCE;
+            let nose_point = position + transform.local_z() * AIM_DISTANCE;
+
+            let size = AIM_DISTANCE * 0.02;
+
+            lines.line_colored(
+                aim_point - Vec3::Y * size,
+                aim_point + Vec3::Y * size,
+                0.0,
+                Color::WHITE,
+            );
+            lines.line_colored(
+                aim_point - right * size,
+                aim_point + right * size,
+                0.0,
+                Color::WHITE,
+            );
+            lines.line_colored(
+                nose_point - transform.local_y() * size * 0.5,
+                nose_point + transform.local_y() * size * 0.5,
+                0.0,
+                Color::GREEN,
+            );
+            lines.line_colored(
+                nose_point - transform.local_x() * size * 0.5,
+                nose_point + transform.local_x() * size * 0.5,
+                0.0,
+                Color::GREEN,
+            );
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use serde::{Deserialize, Serialize};
+
+use super::MassProperties;
+
+/// A place that can carry a variable mass, like a seat or a cargo bay.
+#[derive(Inspectable, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PayloadStation {
+    #[serde(default)]
+    pub name: String,
+    pub position: Vec3,
+    /// Largest mass the station can carry, in kg.
+    pub max_mass: f32,
+    /// Mass the station is loaded with when the plane spawns, in kg.
+    #[serde(default)]
+    pub default_mass: f32,
+}
+
+impl PayloadStation {
+    pub fn initial_mass(&self) -> f32 {
+        self.default_mass.clamp(0.0, self.max_mass)
+    }
+}
+
+/// Allowed range of the center of mass along the longitudinal axis, and the
+/// largest allowed total mass.
+#[derive(Inspectable, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CgEnvelope {
+    /// Most forward allowed center of mass, as a z position in plane space.
+    pub forward_limit: f32,
+    /// Most aft allowed center of mass, as a z position in plane space.
+    pub aft_limit: f32,
+    /// Maximum takeoff mass, in kg.
+    pub max_mass: f32,
+}
+
+impl CgEnvelope {
+    pub fn contains_cg(&self, center_of_mass: Vec3) -> bool {
+        (self.aft_limit..=self.forward_limit).contains(&center_of_mass.z)
+    }
+}
+
+/// Result of a weight-and-balance check.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WeightAndBalance {
+    pub mass_properties: MassProperties,
+    /// Whether the total mass is at most the maximum mass of the envelope.
+    pub mass_ok: bool,
+    /// Whether the center of mass lies within the envelope.
+    pub cg_ok: bool,
+}
+
+impl WeightAndBalance {
+    /// Checks mass properties against an envelope. Without an envelope every
+    /// loading is accepted.
+    pub fn check(mass_properties: MassProperties, envelope: Option<&CgEnvelope>) -> Self {
+        let (mass_ok, cg_ok) = match envelope {
+            Some(envelope) => (
+                mass_properties.mass <= envelope.max_mass,
+                envelope.contains_cg(mass_properties.center_of_mass),
+            ),
+            None => (true, true),
+        };
+
+        Self {
+            mass_properties,
+            mass_ok,
+            cg_ok,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.mass_ok && self.cg_ok
+    }
+}
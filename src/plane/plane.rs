@@ -1,42 +1,253 @@
+use std::{borrow::Cow, collections::HashSet, sync::Arc};
+
 use bevy::{prelude::*, reflect::TypeUuid};
 use bevy_prototype_debug_lines::DebugLines;
-use heron::prelude::*;
+use heron::{prelude::*, rapier_plugin::PhysicsWorld};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     collision_from_mesh::PendingColliders,
+    controls::{Action, ControlAxis, Controls},
+    interaction::{Interactable, InteractionEvent},
+    lift::LiftSources,
     plane::{SurfaceForces, SurfaceInputState},
     player::Player,
-    terrain::TerrainCenter,
+    terrain::{TerrainCenter, TerrainChunks},
+    water::Water,
 };
 
-use super::{PlaneCamera, PlaneSurface};
+use super::{
+    Autopilot, CgEnvelope, Door, EngineState, FuelSelector, FuelTank, Malfunction, Malfunctions,
+    MassProperties, MouseAim, PayloadStation, PlaneBody, PlaneCamera, PlaneDoor, PlaneEngine,
+    PlaneFloat, PlaneSurface, Polar, Seat, SeatRole, WeightAndBalance,
+};
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, TypeUuid)]
 #[uuid = "c5b78858-4882-4dee-b860-87375369de15"]
 pub struct PlaneDescriptor {
-    pub max_speed: f32,
+    /// Empty mass, without fuel or payload.
     pub mass: f32,
+    /// Empty center of mass, without fuel or payload.
     pub center_of_mass: Vec3,
     pub surfaces: Vec<PlaneSurface>,
+    #[serde(skip)]
+    expanded_surfaces: Vec<PlaneSurface>,
+    #[serde(default)]
+    pub bodies: Vec<PlaneBody>,
+    #[serde(default)]
+    pub engines: Vec<PlaneEngine>,
+    #[serde(default)]
+    pub fuel_tanks: Vec<FuelTank>,
+    #[serde(default)]
+    pub payload_stations: Vec<PayloadStation>,
+    #[serde(default)]
+    pub cg_envelope: Option<CgEnvelope>,
+    #[serde(default)]
+    pub floats: Vec<PlaneFloat>,
+    /// Where a tow rope pulling the plane is attached.
+    #[serde(default)]
+    pub tow_hook: Option<Vec3>,
+    /// Where the plane attaches a rope it tows with.
+    #[serde(default)]
+    pub tail_hook: Option<Vec3>,
+    #[serde(default)]
+    pub seats: Vec<Seat>,
+    #[serde(default)]
+    pub doors: Vec<Door>,
+    /// Path and resolved descriptor of the `base` this one was loaded on top
+    /// of.
+    #[serde(skip)]
+    pub base: Option<(String, Arc<PlaneDescriptor>)>,
+}
+
+impl PlaneDescriptor {
+    /// Fills the expanded surfaces from the surfaces and the twins of the
+    /// mirrored ones, after the surfaces changed.
+    pub fn expand_surfaces(&mut self) {
+        self.expanded_surfaces = self
+            .surfaces
+            .iter()
+            .flat_map(|surface| {
+                let mirrored = surface.mirror.then(|| surface.mirrored());
+
+                std::iter::once(surface.clone()).chain(mirrored)
+            })
+            .collect();
+    }
+
+    /// Returns all surfaces, including the twins of mirrored surfaces.
+    pub fn expanded_surfaces(&self) -> &[PlaneSurface] {
+        &self.expanded_surfaces
+    }
+
+    /// Computes mass properties from the empty mass, the fuel in each tank
+    /// and the mass loaded at each payload station.
+    pub fn mass_properties(&self, fuel: &[f32], payload: &[f32]) -> MassProperties {
+        let mut mass_properties = MassProperties::new(self.mass, self.center_of_mass);
+
+        for (tank, fuel) in self.fuel_tanks.iter().zip(fuel) {
+            mass_properties.add(*fuel, tank.position);
+        }
+
+        for (station, payload) in self.payload_stations.iter().zip(payload) {
+            mass_properties.add(*payload, station.position);
+        }
+
+        mass_properties
+    }
+
+    pub fn weight_and_balance(&self, fuel: &[f32], payload: &[f32]) -> WeightAndBalance {
+        WeightAndBalance::check(
+            self.mass_properties(fuel, payload),
+            self.cg_envelope.as_ref(),
+        )
+    }
+
+    pub fn tow_hook(&self) -> Vec3 {
+        self.tow_hook.unwrap_or(self.center_of_mass + Vec3::Z * 2.0)
+    }
+
+    pub fn tail_hook(&self) -> Vec3 {
+        self.tail_hook
+            .unwrap_or(self.center_of_mass - Vec3::Z * 4.0)
+    }
+
+    /// Returns the seats, or a single pilot seat when none are declared.
+    pub fn seats(&self) -> Cow<'_, [Seat]> {
+        if self.seats.is_empty() {
+            Cow::Owned(vec![Seat {
+                name: String::from("pilot"),
+                role: SeatRole::Pilot,
+                position: self.center_of_mass + Vec3::Y * 0.3,
+                door: String::from("door"),
+                crew: false,
+            }])
+        } else {
+            Cow::Borrowed(&self.seats)
+        }
+    }
+
+    /// Returns the doors, or a single door on the right when none are
+    /// declared.
+    pub fn doors(&self) -> Cow<'_, [Door]> {
+        if self.doors.is_empty() {
+            Cow::Owned(vec![Door {
+                name: String::from("door"),
+                position: self.center_of_mass - Vec3::X * 1.0,
+                exit_direction: -Vec3::X,
+            }])
+        } else {
+            Cow::Borrowed(&self.doors)
+        }
+    }
+
+    /// Returns the names of all groups, sorted.
+    pub fn group_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .expanded_surfaces()
+            .iter()
+            .flat_map(|surface| surface.groups.clone())
+            .collect();
+
+        names.sort();
+        names.dedup();
+
+        names
+    }
 }
 
 #[derive(Component, Clone, Debug, Default)]
 pub struct Plane {
-    pub speed: f32,
     pub descriptor: Handle<PlaneDescriptor>,
     pub entered: bool,
+    pub spoilers: bool,
+    pub airbrakes: bool,
+    pub slats: bool,
+    /// Pitch, yaw and roll input, set from the keyboard while the player has
+    /// the controls and by an [`Autopilot`] otherwise.
+    pub input: SurfaceInputState,
+    pub engines: Vec<EngineState>,
+    /// Fuel in each tank, in kg.
+    pub fuel: Vec<f32>,
+    pub fuel_selector: FuelSelector,
+    /// Mass loaded at each payload station, in kg.
+    pub payload: Vec<f32>,
+    /// Mass properties as of the last flight update.
+    pub mass: MassProperties,
+    pub malfunctions: Malfunctions,
+    /// Structural damage from 0 to 1, floats of a damaged plane flood.
+    pub damage: f32,
+    /// Players in each seat, kept while their entities are despawned.
+    pub occupants: Vec<Option<Player>>,
+    /// Seat of the player controlling the game while [`entered`](Self::entered).
+    pub seat: Option<usize>,
+    /// Seat whose occupant has the controls.
+    pub control_seat: Option<usize>,
 }
 
+/// Distance from a door within which the player can enter through it.
+const ENTER_RANGE: f32 = 2.5;
+
 impl Plane {
+    /// Returns whether the player is in the seat that has the controls.
+    pub fn has_control(&self) -> bool {
+        self.entered && self.seat.is_some() && self.seat == self.control_seat
+    }
+
+    /// Seats `player`, taking the controls if the seat has them and nobody
+    /// else does.
+    pub fn board(&mut self, seat: usize, role: SeatRole, player: Player) {
+        if self.occupants.len() <= seat {
+            self.occupants.resize(seat + 1, None);
+        }
+
+        self.occupants[seat] = Some(player);
+
+        if self.control_seat.is_none() && role.has_controls() {
+            self.control_seat = Some(seat);
+        }
+    }
+
+    /// Empties `seat`, releasing the controls if it had them.
+    pub fn leave(&mut self, seat: usize) -> Option<Player> {
+        if self.control_seat == Some(seat) {
+            self.control_seat = None;
+            self.input = SurfaceInputState::default();
+        }
+
+        self.occupants.get_mut(seat).and_then(Option::take)
+    }
+
+    /// Hands the controls from `seat` to another occupied seat with controls,
+    /// returns the seat taking them.
+    pub fn hand_over(&mut self, seats: &[Seat], seat: usize) -> Option<usize> {
+        let other = (0..seats.len())
+            .find(|&i| i != seat && seats[i].role.has_controls() && !self.is_free(i))?;
+
+        self.control_seat = Some(other);
+        self.input = SurfaceInputState::default();
+
+        Some(other)
+    }
+
+    pub fn is_free(&self, seat: usize) -> bool {
+        self.occupants.get(seat).map_or(true, Option::is_none)
+    }
+
+    /// Returns the first free seat reached through `door`.
+    pub fn free_seat(&self, seats: &[Seat], door: &str) -> Option<usize> {
+        (0..seats.len()).find(|&i| seats[i].door == door && self.is_free(i))
+    }
+
     pub fn spawn(
         self,
         commands: &mut Commands,
         asset_server: &AssetServer,
+        descriptor: &str,
         transform: Transform,
     ) -> Entity {
         let scene = asset_server.load("models/plane.glb#Scene0");
-        let descriptor = asset_server.load("planes/basic.plane.ron");
+        let descriptor = asset_server.load(descriptor);
 
         commands
             .spawn()
@@ -45,6 +256,7 @@ impl Plane {
             .insert(RigidBody::Dynamic)
             .insert(Velocity::default())
             .insert(PendingColliders)
+            .insert(MouseAim::default())
             .insert(Plane {
                 descriptor,
                 ..Default::default()
@@ -68,116 +280,196 @@ impl Plane {
             let descriptor = if let Some(d) = descriptors.get(&plane.descriptor) {
                 d
             } else {
-                return;
+                continue;
             };
 
-            for surface in descriptor.surfaces.iter() {
-                let position = *transform * surface.position;
-                let rotation = transform.rotation * surface.rotation_quat();
-
-                let local_z = rotation * Vec3::Z;
-                let local_x = rotation * Vec3::X;
-
-                let t = local_z * surface.chord / 2.0;
-                let r = local_x * surface.span / 2.0;
-
-                let f = surface.flap_fraction;
-
-                let tr = t + r;
-                let tl = t - r;
-                let br = -t + r;
-                let bl = -t - r;
-                let r = Vec3::lerp(br, tr, f);
-                let l = Vec3::lerp(bl, tl, f);
-
-                lines.line_colored(position + tr, position + tl, 0.0, Color::BLUE);
-                lines.line_colored(position + tl, position + l, 0.0, Color::BLUE);
-                lines.line_colored(position + tr, position + r, 0.0, Color::BLUE);
-                lines.line_colored(position + r, position + l, 0.0, Color::BLUE);
-                lines.line_colored(position + br, position + bl, 0.0, Color::YELLOW_GREEN);
-                lines.line_colored(position + bl, position + l, 0.0, Color::YELLOW_GREEN);
-                lines.line_colored(position + br, position + r, 0.0, Color::YELLOW_GREEN);
+            for surface in descriptor.expanded_surfaces() {
+                surface.debug_draw(transform, Color::BLUE, Color::YELLOW_GREEN, &mut lines);
             }
         }
     }
 
     pub fn flight_system(
         time: Res<Time>,
-        key_input: Res<Input<KeyCode>>,
+        controls: Res<Controls>,
         descriptors: Res<Assets<PlaneDescriptor>>,
+        polars: Res<Assets<Polar>>,
+        lift_sources: Res<LiftSources>,
+        terrain: Res<TerrainChunks>,
+        water: Res<Water>,
         mut lines: ResMut<DebugLines>,
-        mut query: Query<(&mut Plane, &mut Velocity, &GlobalTransform)>,
+        mut query: Query<(
+            &mut Plane,
+            &mut Velocity,
+            &GlobalTransform,
+            Option<&MouseAim>,
+        )>,
     ) {
-        for (mut plane, mut velocity, transform) in query.iter_mut() {
-            let mut input = SurfaceInputState::default();
+        let elapsed = time.seconds_since_startup() as f32;
 
+        for (mut plane, mut velocity, transform, mouse_aim) in query.iter_mut() {
             let descriptor = if let Some(d) = descriptors.get(&plane.descriptor) {
                 d
             } else {
-                return;
+                continue;
             };
 
-            if plane.entered {
-                if key_input.pressed(KeyCode::LShift) {
-                    plane.speed += descriptor.max_speed * 0.5 * time.delta_seconds();
+            let plane = &mut *plane;
+
+            plane
+                .engines
+                .resize(descriptor.engines.len(), EngineState::default());
+
+            if plane.fuel.len() != descriptor.fuel_tanks.len() {
+                plane.fuel = descriptor
+                    .fuel_tanks
+                    .iter()
+                    .map(FuelTank::initial_fuel)
+                    .collect();
+            }
+
+            if plane.payload.len() != descriptor.payload_stations.len() {
+                plane.payload = descriptor
+                    .payload_stations
+                    .iter()
+                    .map(PayloadStation::initial_mass)
+                    .collect();
+            }
+
+            // the crew take their seats once the plane is loaded, seats added
+            // in the editor are crewed as they appear
+            let seats = descriptor.seats();
+
+            if plane.occupants.len() < seats.len() {
+                let first = plane.occupants.len();
+                plane.occupants.resize(seats.len(), None);
+
+                for (i, seat) in seats.iter().enumerate().skip(first) {
+                    if seat.crew {
+                        plane.board(i, seat.role, Player::default());
+                    }
+                }
+            }
+
+            if plane.has_control() {
+                if controls.just_pressed(Action::Spoilers) {
+                    plane.spoilers = !plane.spoilers;
                 }
 
-                if key_input.pressed(KeyCode::LControl) {
-                    plane.speed -= descriptor.max_speed * 0.5 * time.delta_seconds();
+                if controls.just_pressed(Action::Airbrakes) {
+                    plane.airbrakes = !plane.airbrakes;
                 }
 
-                plane.speed = plane.speed.clamp(0.0, descriptor.max_speed);
+                if controls.just_pressed(Action::Slats) {
+                    plane.slats = !plane.slats;
+                }
+
+                if controls.just_pressed(Action::FuelSelector) {
+                    plane.fuel_selector = plane.fuel_selector.next(plane.fuel.len());
+                }
 
-                if key_input.pressed(KeyCode::W) {
-                    input.pitch += 1.0;
+                for (i, engine) in plane.engines.iter_mut().enumerate() {
+                    if controls.just_pressed(Action::ToggleEngine(i + 1)) {
+                        engine.selected = !engine.selected;
+                    }
                 }
 
-                if key_input.pressed(KeyCode::S) {
-                    input.pitch -= 1.0;
+                if controls.just_pressed(Action::SelectAllEngines) {
+                    for engine in plane.engines.iter_mut() {
+                        engine.selected = true;
+                    }
                 }
 
-                if key_input.pressed(KeyCode::A) {
-                    input.yaw += 1.0;
+                let throttle =
+                    controls.flight_axis(ControlAxis::Throttle) * 0.5 * time.delta_seconds();
+                let mut mixture = 0.0;
+
+                if controls.pressed(Action::MixtureRich) {
+                    mixture += 0.5 * time.delta_seconds();
                 }
 
-                if key_input.pressed(KeyCode::D) {
-                    input.yaw -= 1.0;
+                if controls.pressed(Action::MixtureLean) {
+                    mixture -= 0.5 * time.delta_seconds();
                 }
 
-                if key_input.pressed(KeyCode::Q) {
-                    input.roll += 1.0;
+                let ignition = controls.just_pressed(Action::Ignition);
+                let afterburner = controls.just_pressed(Action::Afterburner);
+
+                for engine in plane.engines.iter_mut().filter(|engine| engine.selected) {
+                    engine.throttle = controls
+                        .throttle_lever
+                        .unwrap_or_else(|| (engine.throttle + throttle).clamp(0.0, 1.0));
+                    engine.mixture = (engine.mixture + mixture).clamp(0.0, 1.0);
+
+                    if ignition {
+                        engine.running = !engine.running;
+                    }
+
+                    if afterburner {
+                        engine.afterburner = !engine.afterburner;
+                    }
                 }
 
-                if key_input.pressed(KeyCode::E) {
-                    input.roll -= 1.0;
+                // positive roll and yaw turn the plane to the left
+                let mut input = SurfaceInputState {
+                    pitch: controls.flight_axis(ControlAxis::Pitch),
+                    roll: -controls.flight_axis(ControlAxis::Roll),
+                    yaw: -controls.flight_axis(ControlAxis::Yaw),
+                    ..Default::default()
+                };
+
+                // the controls still work on top of mouse aim, to override it
+                if let Some(mouse_aim) = mouse_aim.filter(|mouse_aim| mouse_aim.enabled) {
+                    let steer = mouse_aim.steer(transform, velocity.angular.into());
+
+                    input.pitch = (input.pitch + steer.pitch).clamp(-1.0, 1.0);
+                    input.roll = (input.roll + steer.roll).clamp(-1.0, 1.0);
+                    input.yaw = (input.yaw + steer.yaw).clamp(-1.0, 1.0);
                 }
+
+                plane.input = input;
             }
 
+            let mut input = plane.input.clone();
+            input.spoiler = if plane.spoilers { 1.0 } else { 0.0 };
+            input.airbrake = if plane.airbrakes { 1.0 } else { 0.0 };
+            input.slat = if plane.slats { 1.0 } else { 0.0 };
+
+            plane.malfunctions.update(time.delta_seconds());
+
+            let mass_properties = descriptor.mass_properties(&plane.fuel, &plane.payload);
+            plane.mass = mass_properties;
+
             let angular_velocity: Vec3 = velocity.angular.into();
-            let center_of_mass = *transform * descriptor.center_of_mass;
+            let center_of_mass = *transform * mass_properties.center_of_mass;
 
-            let air_density = f32::clamp(1.0 - (center_of_mass.y / 1000.0), 0.0, 1.0);
+            let lift = lift_sources.around(transform.translation, &terrain);
 
             let mut forces = SurfaceForces::default();
-            for surface in descriptor.surfaces.iter() {
+            for (i, surface) in descriptor.expanded_surfaces().iter().enumerate() {
                 let position = *transform * surface.position;
                 let relative_position = position - center_of_mass;
                 let rotation = transform.rotation * surface.rotation_quat();
 
-                let mut wind = transform.local_z() * -50.0;
-                wind.y = 0.0;
+                let wind = lift.air_velocity(position, elapsed);
 
                 let air_density = f32::clamp(1.0 - (position.y / 1000.0), 0.0, 1.0);
 
-                let flap_angle = surface.input_flap_angle(&input);
+                let (flap_angle, deployment) = plane.malfunctions.surface_controls(
+                    i,
+                    &surface,
+                    surface.input_flap_angle(&input),
+                    surface.input_deployment(&input),
+                );
                 let surface_forces = surface.calculate_forces(
-                    -velocity.linear - Vec3::cross(angular_velocity, relative_position),
-                    //wind,
+                    wind - velocity.linear - Vec3::cross(angular_velocity, relative_position),
                     air_density, // air density
                     relative_position,
                     position,
                     rotation,
                     flap_angle.to_radians(),
+                    deployment,
+                    surface.polar(&polars),
                     &mut lines,
                 );
 
@@ -185,8 +477,165 @@ impl Plane {
                 forces.angular += surface_forces.angular;
             }
 
+            for body in descriptor.bodies.iter() {
+                let position = *transform * body.position;
+                let relative_position = position - center_of_mass;
+                let rotation = transform.rotation * body.rotation_quat();
+
+                let air_density = f32::clamp(1.0 - (position.y / 1000.0), 0.0, 1.0);
+                let wind = lift.air_velocity(position, elapsed);
+
+                let body_forces = body.calculate_forces(
+                    wind - velocity.linear - Vec3::cross(angular_velocity, relative_position),
+                    air_density,
+                    relative_position,
+                    position,
+                    rotation,
+                    &mut lines,
+                );
+
+                forces.linear += body_forces.linear;
+                forces.angular += body_forces.angular;
+            }
+
+            // water drag is limited to what stops the plane within a step,
+            // shared between the parts in the water
+            let wet_parts = descriptor.floats.len() + descriptor.bodies.len();
+            let max_water_drag = |point_velocity: Vec3| {
+                mass_properties.mass * point_velocity.length()
+                    / (f32::max(time.delta_seconds(), 0.001) * wet_parts.max(1) as f32)
+            };
+
+            for float in descriptor.floats.iter() {
+                let position = *transform * float.position;
+                let relative_position = position - center_of_mass;
+                let point_velocity =
+                    velocity.linear + Vec3::cross(angular_velocity, relative_position);
+
+                let float_forces = float.calculate_forces(
+                    -point_velocity,
+                    water.level,
+                    water.density,
+                    1.0 - plane.damage,
+                    max_water_drag(point_velocity),
+                    relative_position,
+                    position,
+                    transform.rotation,
+                    &mut lines,
+                );
+
+                forces.linear += float_forces.linear;
+                forces.angular += float_forces.angular;
+            }
+
+            // the rest of the airframe drags through the water and is damaged
+            // when it hits the surface too hard
+            let mut impact_speed: f32 = 0.0;
+
+            for body in descriptor.bodies.iter() {
+                let position = *transform * body.position;
+                let relative_position = position - center_of_mass;
+                let point_velocity =
+                    velocity.linear + Vec3::cross(angular_velocity, relative_position);
+
+                if water.depth(position) <= 0.0 {
+                    continue;
+                }
+
+                let body_forces = body.calculate_forces(
+                    -point_velocity,
+                    water.density,
+                    relative_position,
+                    position,
+                    transform.rotation * body.rotation_quat(),
+                    &mut lines,
+                );
+
+                let max_drag = max_water_drag(point_velocity);
+                let scale = f32::min(max_drag / body_forces.linear.length().max(1.0), 1.0);
+
+                forces.linear += body_forces.linear * scale;
+                forces.angular += body_forces.angular * scale;
+            }
+
+            let wetted_points = descriptor
+                .bodies
+                .iter()
+                .map(|body| body.position)
+                .chain(descriptor.expanded_surfaces().iter().map(|s| s.position));
+
+            for point in wetted_points {
+                let position = *transform * point;
+                let point_velocity =
+                    velocity.linear + Vec3::cross(angular_velocity, position - center_of_mass);
+                let previous = position - point_velocity * time.delta_seconds();
+
+                if water.depth(position) > 0.0 && water.depth(previous) <= 0.0 {
+                    impact_speed = impact_speed.max(-point_velocity.y);
+                }
+            }
+
+            if impact_speed > water.max_impact_speed {
+                let severity = impact_speed / water.max_impact_speed - 1.0;
+
+                plane.damage = f32::min(plane.damage + severity * 0.5, 1.0);
+
+                if plane.damage >= 1.0 {
+                    for engine in 0..descriptor.engines.len() {
+                        plane
+                            .malfunctions
+                            .trigger(Malfunction::EngineOut { engine });
+                    }
+                } else if let Some(malfunction) = Malfunctions::random(descriptor) {
+                    plane.malfunctions.trigger(malfunction);
+                }
+            }
+
+            // planes without tanks never run out of fuel
+            let starved =
+                !descriptor.fuel_tanks.is_empty() && !plane.fuel_selector.has_fuel(&plane.fuel);
+
+            plane.malfunctions.apply_engines(&mut plane.engines);
+
+            for (engine, state) in descriptor.engines.iter().zip(plane.engines.iter_mut()) {
+                state.starved = starved;
+
+                let position = *transform * engine.position;
+                let relative_position = position - center_of_mass;
+
+                let air_density = f32::clamp(1.0 - (position.y / 1000.0), 0.0, 1.0);
+
+                let thrust = state.update(
+                    engine,
+                    air_density,
+                    (velocity.linear - lift_sources.wind).length(),
+                    time.delta_seconds(),
+                );
+                let engine_forces =
+                    engine.calculate_forces(thrust, relative_position, transform.rotation);
+
+                if cfg!(feature = "debug") {
+                    lines.line_colored(
+                        position,
+                        position - engine_forces.linear * 0.01,
+                        0.0,
+                        Color::ORANGE,
+                    );
+                }
+
+                forces.linear += engine_forces.linear;
+                forces.angular += engine_forces.angular;
+            }
+
+            let fuel_flow: f32 = plane.engines.iter().map(|engine| engine.fuel_flow).sum();
+            let fuel_selector = plane.fuel_selector;
+            fuel_selector.draw(&mut plane.fuel, fuel_flow * time.delta_seconds());
+            plane
+                .malfunctions
+                .leak(&mut plane.fuel, time.delta_seconds());
+
             let mut sim_forces = SurfaceForces::default();
-            for surface in descriptor.surfaces.iter() {
+            for (i, surface) in descriptor.expanded_surfaces().iter().enumerate() {
                 let position = *transform * surface.position;
                 let relative_position = position - center_of_mass;
                 let rotation = transform.rotation * surface.rotation_quat();
@@ -198,7 +647,12 @@ impl Plane {
 
                 let air_density = f32::clamp(1.0 - (position.y / 1000.0), 0.0, 1.0);
 
-                let flap_angle = surface.input_flap_angle(&input);
+                let (flap_angle, deployment) = plane.malfunctions.surface_controls(
+                    i,
+                    &surface,
+                    surface.input_flap_angle(&input),
+                    surface.input_deployment(&input),
+                );
                 let surface_forces = surface.calculate_forces(
                     wind,
                     air_density,
@@ -206,6 +660,8 @@ impl Plane {
                     position,
                     rotation,
                     flap_angle.to_radians(),
+                    deployment,
+                    surface.polar(&polars),
                     &mut lines,
                 );
 
@@ -233,59 +689,165 @@ impl Plane {
                 );
             }
 
-            velocity.linear += forces.linear * time.delta_seconds() / descriptor.mass;
+            velocity.linear += forces.linear * time.delta_seconds() / mass_properties.mass;
             velocity.angular = From::from(
-                angular_velocity + forces.angular * time.delta_seconds() / descriptor.mass,
+                angular_velocity + forces.angular * time.delta_seconds() / mass_properties.mass,
             );
-
-            velocity.linear +=
-                transform.local_z() * plane.speed * air_density * time.delta_seconds();
         }
     }
 
     pub fn enter_system(
         mut commands: Commands,
-        key_input: Res<Input<KeyCode>>,
-        plane_camera_query: Query<Entity, With<PlaneCamera>>,
+        controls: Res<Controls>,
+        descriptors: Res<Assets<PlaneDescriptor>>,
+        physics_world: PhysicsWorld,
+        mut plane_camera_query: Query<(Entity, &Parent, &mut PlaneCamera)>,
         mut plane_query: Query<(Entity, &mut Plane, &GlobalTransform)>,
-        player_query: Query<(Entity, &Player, &GlobalTransform)>,
+        velocity_query: Query<&Velocity, With<Plane>>,
+        player_query: Query<&Player>,
+        door_query: Query<(&PlaneDoor, &Parent)>,
+        mut interaction_events: EventReader<InteractionEvent>,
     ) {
-        let (plane_entity, mut plane, plane_transform) =
-            if let Ok(components) = plane_query.get_single_mut() {
-                components
-            } else {
-                return;
+        let entered = plane_query
+            .iter()
+            .find(|(_, plane, _)| plane.entered)
+            .map(|(entity, _, _)| entity);
+
+        if let Some(plane_entity) = entered {
+            let (_, mut plane, plane_transform) = plane_query.get_mut(plane_entity).unwrap();
+
+            let (descriptor, seat) = match (descriptors.get(&plane.descriptor), plane.seat) {
+                (Some(descriptor), Some(seat)) => (descriptor, seat),
+                _ => return,
             };
 
-        if plane.entered {
-            if key_input.just_pressed(KeyCode::Return) {
-                commands.entity(plane_entity).remove::<TerrainCenter>();
+            let seats = descriptor.seats();
+
+            if controls.just_pressed(Action::ExitVehicle) {
+                let doors = descriptor.doors();
+                let door = seats
+                    .get(seat)
+                    .and_then(|seat| doors.iter().find(|door| door.name == seat.door))
+                    .or_else(|| doors.first());
+
+                let candidates: Vec<Vec3> = door
+                    .into_iter()
+                    .flat_map(Door::exit_candidates)
+                    .map(|candidate| *plane_transform * candidate)
+                    .collect();
+
+                let had_control = plane.control_seat == Some(seat);
+                let center_of_mass = *plane_transform * plane.mass.center_of_mass;
+
+                let exited = Player::exit_vehicle(
+                    &mut commands,
+                    &physics_world,
+                    &candidates,
+                    velocity_query.get(plane_entity).ok(),
+                    center_of_mass,
+                    || plane.leave(seat).unwrap_or_default(),
+                );
 
-                let mut translation = plane_transform.translation
-                    + plane_transform.local_x() * -2.0
-                    + plane_transform.local_z() * -2.0;
+                if exited.is_none() {
+                    return;
+                }
 
-                translation.y = plane_transform.translation.y + 1.0;
+                commands.entity(plane_entity).remove::<TerrainCenter>();
 
-                Player::default().spawn(&mut commands, Transform::from_translation(translation));
+                // the crew keeps flying the plane the player left
+                if had_control && plane.hand_over(&seats, seat).is_some() {
+                    commands
+                        .entity(plane_entity)
+                        .insert(Autopilot::holding(&plane, plane_transform));
+                }
 
                 plane.entered = false;
+                plane.seat = None;
 
-                let entity = plane_camera_query.single();
-
-                commands.entity(entity).despawn_recursive();
+                for (entity, parent, _) in plane_camera_query.iter() {
+                    if parent.0 == plane_entity {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+            } else if controls.just_pressed(Action::HandOverControl) {
+                if plane.control_seat == Some(seat) {
+                    if plane.hand_over(&seats, seat).is_some() {
+                        commands
+                            .entity(plane_entity)
+                            .insert(Autopilot::holding(&plane, plane_transform));
+                    }
+                } else if seats
+                    .get(seat)
+                    .map_or(false, |seat| seat.role.has_controls())
+                {
+                    plane.control_seat = Some(seat);
+                }
+            } else if controls.just_pressed(Action::ChangeSeat) {
+                let next = (1..seats.len())
+                    .map(|offset| (seat + offset) % seats.len())
+                    .find(|&i| plane.is_free(i));
+
+                if let Some(next) = next {
+                    let had_control = plane.control_seat == Some(seat);
+                    let player = plane.leave(seat).unwrap_or_default();
+
+                    plane.board(next, seats[next].role, player);
+                    plane.seat = Some(next);
+
+                    if had_control && seats[next].role.has_controls() {
+                        plane.control_seat = Some(next);
+                    } else if had_control && plane.hand_over(&seats, next).is_some() {
+                        commands
+                            .entity(plane_entity)
+                            .insert(Autopilot::holding(&plane, plane_transform));
+                    }
+
+                    for (_, parent, mut camera) in plane_camera_query.iter_mut() {
+                        if parent.0 == plane_entity {
+                            camera.target = seats[next].position;
+                        }
+                    }
+                }
             }
         } else {
-            let (player_entity, _player, player_transform) = player_query.single();
+            for event in interaction_events.iter() {
+                let (door, plane_entity) = match door_query.get(event.target) {
+                    Ok((door, parent)) => (door, parent.0),
+                    Err(_) => continue,
+                };
+
+                let player = match player_query.get(event.player) {
+                    Ok(player) => player,
+                    Err(_) => continue,
+                };
+
+                let (_, mut plane, _) = match plane_query.get_mut(plane_entity) {
+                    Ok(plane) => plane,
+                    Err(_) => continue,
+                };
+
+                let seats = match descriptors.get(&plane.descriptor) {
+                    Some(descriptor) => descriptor.seats(),
+                    None => continue,
+                };
+
+                let seat = match plane.free_seat(&seats, &door.name) {
+                    Some(seat) => seat,
+                    None => continue,
+                };
 
-            let distance = plane_transform
-                .translation
-                .distance(player_transform.translation);
+                let seat_descriptor = seats[seat].clone();
 
-            if distance < 4.0 && key_input.just_pressed(KeyCode::Return) {
-                commands.entity(player_entity).despawn_recursive();
+                commands.entity(event.player).despawn_recursive();
 
+                plane.board(seat, seat_descriptor.role, player.clone());
                 plane.entered = true;
+                plane.seat = Some(seat);
+
+                // the player takes the controls over from the crew
+                if seat_descriptor.role.has_controls() {
+                    plane.control_seat = Some(seat);
+                }
 
                 commands
                     .entity(plane_entity)
@@ -293,8 +855,92 @@ impl Plane {
                     .with_children(|parent| {
                         parent
                             .spawn_bundle(PerspectiveCameraBundle::default())
-                            .insert(PlaneCamera::default());
+                            .insert(PlaneCamera {
+                                target: seat_descriptor.position,
+                                ..Default::default()
+                            });
                     });
+
+                break;
+            }
+        }
+    }
+
+    /// Keeps a [`PlaneDoor`] at every door, which can be interacted with while
+    /// a seat behind it is free.
+    pub fn door_system(
+        mut commands: Commands,
+        descriptors: Res<Assets<PlaneDescriptor>>,
+        plane_query: Query<(Entity, &Plane)>,
+        mut door_query: Query<(&mut PlaneDoor, &Parent, &mut Interactable, &mut Transform)>,
+    ) {
+        let mut spawned = HashSet::new();
+
+        for (mut door, parent, mut interactable, mut transform) in door_query.iter_mut() {
+            let plane = if let Ok((_, plane)) = plane_query.get(parent.0) {
+                plane
+            } else {
+                continue;
+            };
+
+            spawned.insert((parent.0, door.name.clone()));
+
+            let descriptor = if let Some(d) = descriptors.get(&plane.descriptor) {
+                d
+            } else {
+                continue;
+            };
+
+            let seats = descriptor.seats();
+
+            // a door removed in the editor has no seats behind it and stays
+            // disabled
+            let free_seat = plane
+                .free_seat(&seats, &door.name)
+                .map(|seat| &seats[seat].name);
+
+            if door.shown_seat.as_ref() != free_seat {
+                door.shown_seat = free_seat.cloned();
+                interactable.enabled = free_seat.is_some();
+
+                if let Some(seat) = free_seat {
+                    interactable.prompt = format!("Enter as {}", seat);
+                }
+            }
+
+            if let Some(descriptor_door) = descriptor.doors().iter().find(|d| d.name == door.name) {
+                if transform.translation != descriptor_door.position {
+                    transform.translation = descriptor_door.position;
+                }
+            }
+        }
+
+        for (plane_entity, plane) in plane_query.iter() {
+            let descriptor = if let Some(d) = descriptors.get(&plane.descriptor) {
+                d
+            } else {
+                continue;
+            };
+
+            for door in descriptor.doors().iter() {
+                if spawned.contains(&(plane_entity, door.name.clone())) {
+                    continue;
+                }
+
+                commands.entity(plane_entity).with_children(|parent| {
+                    parent
+                        .spawn()
+                        .insert(Transform::from_translation(door.position))
+                        .insert(GlobalTransform::identity())
+                        .insert(PlaneDoor {
+                            name: door.name.clone(),
+                            shown_seat: None,
+                        })
+                        .insert(Interactable {
+                            enabled: false,
+                            ..Interactable::new("Enter", ENTER_RANGE)
+                        });
+                });
             }
         }
     }
@@ -0,0 +1,296 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::{
+    asset::{AssetLoader, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+
+/// Angle over which a polar blends from the edge of its table to a flat plate.
+const FLAT_PLATE_BLEND: f32 = 10.0 * PI / 180.0;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PolarPoint {
+    /// Angle of attack in radians.
+    pub alpha: f32,
+    pub lift: f32,
+    pub drag: f32,
+    pub moment: f32,
+}
+
+impl PolarPoint {
+    fn coefficients(&self) -> Vec3 {
+        Vec3::new(self.lift, self.drag, self.moment)
+    }
+}
+
+/// A single polar, coefficients against angle of attack at a fixed Reynolds
+/// number.
+#[derive(Clone, Debug, Default)]
+pub struct PolarCurve {
+    pub reynolds: f32,
+    pub points: Vec<PolarPoint>,
+}
+
+impl PolarCurve {
+    pub fn sample(&self, alpha: f32) -> Vec3 {
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+
+        if alpha < first.alpha {
+            let t = f32::min((first.alpha - alpha) / FLAT_PLATE_BLEND, 1.0);
+            return Vec3::lerp(first.coefficients(), flat_plate(alpha), t);
+        }
+
+        if alpha > last.alpha {
+            let t = f32::min((alpha - last.alpha) / FLAT_PLATE_BLEND, 1.0);
+            return Vec3::lerp(last.coefficients(), flat_plate(alpha), t);
+        }
+
+        let i = self.points.partition_point(|point| point.alpha < alpha);
+
+        if i == 0 {
+            return first.coefficients();
+        }
+
+        let a = self.points[i - 1];
+        let b = self.points[i];
+        let t = (alpha - a.alpha) / (b.alpha - a.alpha);
+
+        Vec3::lerp(a.coefficients(), b.coefficients(), t)
+    }
+}
+
+/// All polars recorded at one flap deflection, sorted by Reynolds number.
+#[derive(Clone, Debug, Default)]
+pub struct PolarFlap {
+    /// Flap deflection in radians.
+    pub flap_angle: f32,
+    pub curves: Vec<PolarCurve>,
+}
+
+impl PolarFlap {
+    pub fn sample(&self, alpha: f32, reynolds: f32) -> Vec3 {
+        let (a, b, t) = bracket(&self.curves, reynolds, |curve| curve.reynolds);
+
+        Vec3::lerp(
+            self.curves[a].sample(alpha),
+            self.curves[b].sample(alpha),
+            t,
+        )
+    }
+}
+
+/// Tabulated airfoil coefficients, loaded from XFoil polar output.
+///
+/// A `.polar` file may contain several XFoil polars one after another. Each
+/// takes its Reynolds number from the `Re =` line of its header, and its flap
+/// deflection in degrees from an optional `Flap =` line.
+#[derive(Clone, Debug, Default, TypeUuid)]
+#[uuid = "0b2e6a0f-4f0c-4a57-9a0e-3c1d8f2b7e61"]
+pub struct Polar {
+    /// Polars grouped by flap deflection, sorted by flap angle.
+    pub flaps: Vec<PolarFlap>,
+}
+
+impl Polar {
+    /// Returns whether the table contains polars for more than one flap
+    /// deflection.
+    pub fn has_flap_curves(&self) -> bool {
+        self.flaps.len() > 1
+    }
+
+    /// Returns lift, drag and moment coefficients.
+    pub fn sample(&self, alpha: f32, flap_angle: f32, reynolds: f32) -> Vec3 {
+        let (a, b, t) = bracket(&self.flaps, flap_angle, |flap| flap.flap_angle);
+
+        Vec3::lerp(
+            self.flaps[a].sample(alpha, reynolds),
+            self.flaps[b].sample(alpha, reynolds),
+            t,
+        )
+    }
+
+    pub fn parse(text: &str) -> Result<Self, anyhow::Error> {
+        let mut curves: Vec<(f32, PolarCurve)> = Vec::new();
+
+        let mut flap_angle = 0.0;
+        let mut reynolds = 0.0;
+        let mut columns = None;
+        let mut points = Vec::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+
+            let re = header_value(line, "Re =");
+            let flap = header_value(line, "Flap =");
+
+            let header = re.is_some() || flap.is_some() || line.starts_with("alpha");
+
+            if header && !points.is_empty() {
+                let points = std::mem::take(&mut points);
+                curves.push((flap_angle, PolarCurve { reynolds, points }));
+            }
+
+            if let Some(re) = re {
+                reynolds = re;
+            }
+
+            if let Some(flap) = flap {
+                flap_angle = flap.to_radians();
+            }
+
+            if line.starts_with("alpha") {
+                columns = Some(PolarColumns::parse(line)?);
+                continue;
+            }
+
+            if let Some(ref columns) = columns {
+                if let Some(point) = columns.parse_point(line) {
+                    points.push(point);
+                }
+            }
+        }
+
+        if !points.is_empty() {
+            curves.push((flap_angle, PolarCurve { reynolds, points }));
+        }
+
+        if curves.is_empty() {
+            anyhow::bail!("polar contains no data");
+        }
+
+        let mut flaps: Vec<PolarFlap> = Vec::new();
+
+        for (flap_angle, mut curve) in curves {
+            curve
+                .points
+                .sort_by(|a, b| f32::total_cmp(&a.alpha, &b.alpha));
+
+            match flaps.iter_mut().find(|flap| flap.flap_angle == flap_angle) {
+                Some(flap) => flap.curves.push(curve),
+                None => flaps.push(PolarFlap {
+                    flap_angle,
+                    curves: vec![curve],
+                }),
+            }
+        }
+
+        flaps.sort_by(|a, b| f32::total_cmp(&a.flap_angle, &b.flap_angle));
+
+        for flap in flaps.iter_mut() {
+            flap.curves
+                .sort_by(|a, b| f32::total_cmp(&a.reynolds, &b.reynolds));
+        }
+
+        Ok(Self { flaps })
+    }
+}
+
+struct PolarColumns {
+    alpha: usize,
+    lift: usize,
+    drag: usize,
+    moment: usize,
+}
+
+impl PolarColumns {
+    fn parse(line: &str) -> Result<Self, anyhow::Error> {
+        let names: Vec<&str> = line.split_whitespace().collect();
+
+        let column = |name: &str| {
+            names
+                .iter()
+                .position(|column| *column == name)
+                .ok_or_else(|| anyhow::anyhow!("polar is missing column '{}'", name))
+        };
+
+        Ok(Self {
+            alpha: column("alpha")?,
+            lift: column("CL")?,
+            drag: column("CD")?,
+            moment: column("CM")?,
+        })
+    }
+
+    fn parse_point(&self, line: &str) -> Option<PolarPoint> {
+        let values = line
+            .split_whitespace()
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+
+        Some(PolarPoint {
+            alpha: values.get(self.alpha)?.to_radians(),
+            lift: *values.get(self.lift)?,
+            drag: *values.get(self.drag)?,
+            moment: *values.get(self.moment)?,
+        })
+    }
+}
+
+/// Parses a header value such as `Re =     1.000 e 6`.
+fn header_value(line: &str, key: &str) -> Option<f32> {
+    let start = line.find(key)? + key.len();
+    let mut tokens = line[start..].split_whitespace();
+
+    let mantissa: f32 = tokens.next()?.parse().ok()?;
+
+    if tokens.next() == Some("e") {
+        let exponent: i32 = tokens.next()?.parse().ok()?;
+        Some(mantissa * 10f32.powi(exponent))
+    } else {
+        Some(mantissa)
+    }
+}
+
+/// Finds the entries of a sorted slice around `value`, returning their indices
+/// and the interpolation parameter between them.
+fn bracket<T>(items: &[T], value: f32, key: impl Fn(&T) -> f32) -> (usize, usize, f32) {
+    let i = items.partition_point(|item| key(item) < value);
+
+    if i == 0 {
+        (0, 0, 0.0)
+    } else if i == items.len() {
+        (i - 1, i - 1, 0.0)
+    } else {
+        let a = key(&items[i - 1]);
+        let b = key(&items[i]);
+
+        (i - 1, i, (value - a) / (b - a))
+    }
+}
+
+/// Coefficients of a flat plate, used past the ends of a table.
+fn flat_plate(alpha: f32) -> Vec3 {
+    let normal_coefficient = 1.98 * alpha.sin();
+    let folded_alpha = f32::min(alpha.abs(), PI - alpha.abs());
+
+    Vec3::new(
+        normal_coefficient * alpha.cos(),
+        normal_coefficient * alpha.sin(),
+        -normal_coefficient * (0.25 - 0.175 * (1.0 - folded_alpha / FRAC_PI_2)),
+    )
+}
+
+pub struct PolarAssetLoader;
+
+impl AssetLoader for PolarAssetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::asset::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async {
+            let polar = Polar::parse(std::str::from_utf8(bytes)?)?;
+
+            load_context.set_default_asset(LoadedAsset::new(polar));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["polar"]
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use serde::{Deserialize, Serialize};
+
+/// What the occupant of a seat can do.
+#[derive(Inspectable, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SeatRole {
+    Pilot,
+    /// Has a second set of controls, control is handed over between the
+    /// pilot and co-pilot.
+    CoPilot,
+    Passenger,
+}
+
+impl Default for SeatRole {
+    fn default() -> Self {
+        Self::Passenger
+    }
+}
+
+impl SeatRole {
+    pub fn has_controls(self) -> bool {
+        matches!(self, Self::Pilot | Self::CoPilot)
+    }
+}
+
+#[derive(Inspectable, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Seat {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub role: SeatRole,
+    /// Position of the occupant's head.
+    pub position: Vec3,
+    /// Name of the door the seat is reached through.
+    pub door: String,
+    /// Occupied by a crew member once the plane is loaded, who flies with
+    /// the autopilot while they have the controls.
+    #[serde(default)]
+    pub crew: bool,
+}
+
+/// A door or other entry point, players enter and leave through it.
+#[derive(Inspectable, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Door {
+    #[serde(default)]
+    pub name: String,
+    pub position: Vec3,
+    /// Direction a player steps out in, in plane space.
+    pub exit_direction: Vec3,
+}
+
+impl Door {
+    /// Returns the positions to try when leaving through the door, in plane
+    /// space, best first.
+    ///
+    /// Players step out along the exit direction, and try the other side,
+    /// behind and in front of the door when that is blocked.
+    pub fn exit_candidates(&self) -> impl Iterator<Item = Vec3> + '_ {
+        let outward = Vec3::new(self.exit_direction.x, 0.0, self.exit_direction.z);
+        let outward = if outward.length_squared() > 0.0 {
+            outward.normalize()
+        } else {
+            Vec3::X * self.position.x.signum()
+        };
+
+        let directions = [outward, -Vec3::Z, Vec3::Z, -outward];
+
+        directions.into_iter().flat_map(move |direction| {
+            [1.0, 2.0, 4.0]
+                .into_iter()
+                .map(move |distance| self.position + direction * distance)
+        })
+    }
+}
+
+/// Placed at a door of a plane as a child of the plane, so players can
+/// interact with it.
+#[derive(Component, Clone, Debug, Default)]
+pub struct PlaneDoor {
+    /// Name of the door in the descriptor.
+    pub name: String,
+    /// Seat the prompt currently offers, to only rewrite it when it changes.
+    pub shown_seat: Option<String>,
+}
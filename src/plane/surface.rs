@@ -5,12 +5,22 @@ use bevy_inspector_egui::Inspectable;
 use bevy_prototype_debug_lines::DebugLines;
 use serde::{Deserialize, Serialize};
 
+use super::Polar;
+
+/// Density of air at sea level, in kg/m³.
+const SEA_LEVEL_AIR_DENSITY: f32 = 1.225;
+/// Dynamic viscosity of air, in kg/(m·s).
+const AIR_VISCOSITY: f32 = 1.81e-5;
+
 #[derive(Inspectable, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SurfaceInputKind {
     Pitch,
     Yaw,
     Roll,
     Flap,
+    Spoiler,
+    Airbrake,
+    Slat,
     None,
 }
 
@@ -25,6 +35,28 @@ pub struct SurfaceInputState {
     pub pitch: f32,
     pub yaw: f32,
     pub roll: f32,
+    pub spoiler: f32,
+    pub airbrake: f32,
+    pub slat: f32,
+}
+
+/// What a surface does with its input.
+///
+/// `Wing` surfaces deflect a hinged trailing edge flap. The other kinds are
+/// lifting sections fitted with a device that deploys from 0 to 1, except for
+/// `Airbrake` which only produces drag.
+#[derive(Inspectable, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SurfaceKind {
+    Wing,
+    Spoiler,
+    Airbrake,
+    Slat,
+}
+
+impl Default for SurfaceKind {
+    fn default() -> Self {
+        Self::Wing
+    }
 }
 
 #[derive(Inspectable, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -50,8 +82,25 @@ const fn default_lift() -> f32 {
     1.0
 }
 
+const fn default_max_deployment() -> f32 {
+    60.0
+}
+
+const fn default_slat_stall_angle() -> f32 {
+    8.0
+}
+
 #[derive(Inspectable, Clone, Debug, Serialize, Deserialize)]
 pub struct PlaneSurface {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub kind: SurfaceKind,
+    /// Generates a twin of the surface mirrored to the other side of the plane.
+    #[serde(default)]
+    pub mirror: bool,
+    #[serde(default)]
+    pub groups: Vec<String>,
     pub input_kind: SurfaceInputKind,
     pub side: SurfaceSide,
     pub position: Vec3,
@@ -66,11 +115,27 @@ pub struct PlaneSurface {
     pub stall_angle_high: f32,
     pub stall_angle_low: f32,
     pub flap_fraction: f32,
+    /// Deflection of a fully deployed spoiler or airbrake, in degrees.
+    #[serde(default = "default_max_deployment")]
+    pub max_deployment: f32,
+    /// Increase of the stall angle with fully deployed slats, in degrees.
+    #[serde(default = "default_slat_stall_angle")]
+    pub slat_stall_angle: f32,
+    /// Path of a [`Polar`] table used in place of the analytic coefficients.
+    #[serde(default)]
+    pub polar: Option<String>,
+    #[serde(skip)]
+    #[inspectable(ignore)]
+    pub polar_handle: Option<Handle<Polar>>,
 }
 
 impl Default for PlaneSurface {
     fn default() -> Self {
         Self {
+            name: String::new(),
+            kind: SurfaceKind::Wing,
+            mirror: false,
+            groups: Vec::new(),
             input_kind: SurfaceInputKind::None,
             side: SurfaceSide::Center,
             position: Vec3::ZERO,
@@ -84,11 +149,61 @@ impl Default for PlaneSurface {
             stall_angle_high: 20.0,
             stall_angle_low: -15.0,
             flap_fraction: 0.2,
+            max_deployment: default_max_deployment(),
+            slat_stall_angle: default_slat_stall_angle(),
+            polar: None,
+            polar_handle: None,
         }
     }
 }
 
+/// Swaps a leading `left` for `right` and the other way around, or appends
+/// `_mirror` to names without a side.
+pub fn mirror_name(name: &str) -> String {
+    if let Some(rest) = name.strip_prefix("left") {
+        format!("right{}", rest)
+    } else if let Some(rest) = name.strip_prefix("right") {
+        format!("left{}", rest)
+    } else {
+        format!("{}_mirror", name)
+    }
+}
+
 impl PlaneSurface {
+    /// Returns the twin of the surface on the other side of the plane.
+    pub fn mirrored(&self) -> Self {
+        let mut surface = self.clone();
+
+        surface.name = mirror_name(&self.name);
+        surface.mirror = false;
+        surface.side = match self.side {
+            SurfaceSide::Left => SurfaceSide::Right,
+            SurfaceSide::Right => SurfaceSide::Left,
+            SurfaceSide::Center => SurfaceSide::Center,
+        };
+        surface.position.x = -self.position.x;
+        surface.rotation.y = -self.rotation.y;
+        surface.rotation.z = -self.rotation.z;
+        surface.groups = self
+            .groups
+            .iter()
+            .map(|group| {
+                if group.starts_with("left") || group.starts_with("right") {
+                    mirror_name(group)
+                } else {
+                    group.clone()
+                }
+            })
+            .collect();
+
+        surface
+    }
+
+    /// Returns whether the surface is named `group` or belongs to it.
+    pub fn in_group(&self, group: &str) -> bool {
+        self.name == group || self.groups.iter().any(|g| g == group)
+    }
+
     pub fn rotation_quat(&self) -> Quat {
         Quat::from_euler(
             EulerRot::YXZ,
@@ -98,7 +213,50 @@ impl PlaneSurface {
         )
     }
 
+    /// Draws the outline of the surface, with the flap in `flap_color`.
+    pub fn debug_draw(
+        &self,
+        transform: &GlobalTransform,
+        color: Color,
+        flap_color: Color,
+        lines: &mut DebugLines,
+    ) {
+        let position = *transform * self.position;
+        let rotation = transform.rotation * self.rotation_quat();
+
+        let local_z = rotation * Vec3::Z;
+        let local_x = rotation * Vec3::X;
+
+        let t = local_z * self.chord / 2.0;
+        let r = local_x * self.span / 2.0;
+
+        let f = self.flap_fraction;
+
+        let tr = t + r;
+        let tl = t - r;
+        let br = -t + r;
+        let bl = -t - r;
+        let r = Vec3::lerp(br, tr, f);
+        let l = Vec3::lerp(bl, tl, f);
+
+        lines.line_colored(position + tr, position + tl, 0.0, color);
+        lines.line_colored(position + tl, position + l, 0.0, color);
+        lines.line_colored(position + tr, position + r, 0.0, color);
+        lines.line_colored(position + r, position + l, 0.0, color);
+        lines.line_colored(position + br, position + bl, 0.0, flap_color);
+        lines.line_colored(position + bl, position + l, 0.0, flap_color);
+        lines.line_colored(position + br, position + r, 0.0, flap_color);
+    }
+
+    pub fn polar<'a>(&self, polars: &'a Assets<Polar>) -> Option<&'a Polar> {
+        polars.get(self.polar_handle.as_ref()?)
+    }
+
     pub fn input_flap_angle(&self, input: &SurfaceInputState) -> f32 {
+        if self.kind != SurfaceKind::Wing {
+            return 0.0;
+        }
+
         match self.input_kind {
             SurfaceInputKind::Pitch => input.pitch * 6.0,
             SurfaceInputKind::Yaw => -input.yaw * 24.0,
@@ -108,10 +266,33 @@ impl PlaneSurface {
                 SurfaceSide::Center => 0.0,
             },
             SurfaceInputKind::Flap => -input.pitch * 6.0,
-            SurfaceInputKind::None => 0.0,
+            _ => 0.0,
         }
     }
 
+    /// Returns how far the device of a spoiler, airbrake or slat is deployed.
+    ///
+    /// Spoilers driven by roll deploy on the wing that should go down.
+    pub fn input_deployment(&self, input: &SurfaceInputState) -> f32 {
+        if self.kind == SurfaceKind::Wing {
+            return 0.0;
+        }
+
+        let deployment = match self.input_kind {
+            SurfaceInputKind::Spoiler => input.spoiler,
+            SurfaceInputKind::Airbrake => input.airbrake,
+            SurfaceInputKind::Slat => input.slat,
+            SurfaceInputKind::Roll => match self.side {
+                SurfaceSide::Left => f32::max(input.roll, input.spoiler),
+                SurfaceSide::Right => f32::max(-input.roll, input.spoiler),
+                SurfaceSide::Center => input.spoiler,
+            },
+            _ => 0.0,
+        };
+
+        deployment.clamp(0.0, 1.0)
+    }
+
     pub fn calculate_forces(
         &self,
         world_air_velocity: Vec3,
@@ -120,6 +301,8 @@ impl PlaneSurface {
         position: Vec3,
         rotation: Quat,
         flap_angle: f32,
+        deployment: f32,
+        polar: Option<&Polar>,
         lines: &mut DebugLines,
     ) -> SurfaceForces {
         let corrected_lift_slope = self.lift_slope * self.aspect()
@@ -135,7 +318,12 @@ impl PlaneSurface {
         let zero_lift_aoa_base = self.zero_lift_aoa.to_radians();
         let zero_lift_aoa = zero_lift_aoa_base - delta_lift / corrected_lift_slope;
 
-        let stall_angle_high_base = self.stall_angle_high.to_radians();
+        let mut stall_angle_high_base = self.stall_angle_high.to_radians();
+
+        if self.kind == SurfaceKind::Slat {
+            stall_angle_high_base += self.slat_stall_angle.to_radians() * deployment;
+        }
+
         let stall_angle_low_base = self.stall_angle_low.to_radians();
 
         let cl_max_high = corrected_lift_slope * (stall_angle_high_base - zero_lift_aoa_base)
@@ -158,15 +346,41 @@ impl PlaneSurface {
 
         let mut color = Color::BLUE;
 
-        let coefficients = self.calculate_coefficients(
-            angle_of_attack,
-            corrected_lift_slope,
-            zero_lift_aoa,
-            stall_angle_high,
-            stall_angle_low,
-            flap_angle,
-            &mut color,
-        );
+        let mut coefficients = if let Some(polar) = polar {
+            let reynolds = SEA_LEVEL_AIR_DENSITY * air_density * air_velocity.length() * self.chord
+                / AIR_VISCOSITY;
+
+            self.calculate_polar_coefficients(
+                polar,
+                angle_of_attack,
+                zero_lift_aoa_base - zero_lift_aoa,
+                flap_angle,
+                reynolds,
+            )
+        } else {
+            self.calculate_coefficients(
+                angle_of_attack,
+                corrected_lift_slope,
+                zero_lift_aoa,
+                stall_angle_high,
+                stall_angle_low,
+                flap_angle,
+                &mut color,
+            )
+        };
+
+        let deflection = (self.max_deployment * deployment).to_radians();
+
+        match self.kind {
+            SurfaceKind::Spoiler => {
+                coefficients.x *= 1.0 - deflection.sin();
+                coefficients.y += 1.2 * deflection.sin() * deflection.sin();
+            }
+            SurfaceKind::Airbrake => {
+                coefficients = Vec3::new(0.0, self.skin_friction + 1.28 * deflection.sin(), 0.0);
+            }
+            _ => {}
+        }
 
         let lift = lift_direction * coefficients.x * dynamic_pressure * area * self.lift;
         let drag = drag_direction * coefficients.y * dynamic_pressure * area * self.lift;
@@ -271,6 +485,37 @@ impl PlaneSurface {
         coefficients
     }
 
+    /// Samples section coefficients from a polar table and corrects them for
+    /// the aspect ratio of the surface.
+    ///
+    /// Tables with a single flap deflection are shifted by the analytic change
+    /// in zero lift angle of attack, `flap_shift`.
+    fn calculate_polar_coefficients(
+        &self,
+        polar: &Polar,
+        angle_of_attack: f32,
+        flap_shift: f32,
+        flap_angle: f32,
+        reynolds: f32,
+    ) -> Vec3 {
+        let (angle_of_attack, flap_angle) = if polar.has_flap_curves() {
+            (angle_of_attack, flap_angle)
+        } else {
+            (angle_of_attack + flap_shift, 0.0)
+        };
+
+        let section = polar.sample(angle_of_attack, flap_angle, reynolds);
+        let induced_angle = section.x / (PI * self.aspect());
+
+        let coefficients = polar.sample(angle_of_attack - induced_angle, flap_angle, reynolds);
+
+        Vec3::new(
+            coefficients.x,
+            coefficients.y + coefficients.x * induced_angle,
+            coefficients.z,
+        )
+    }
+
     fn calculate_coefficients_at_low_aoa(
         &self,
         angle_of_attack: f32,
@@ -1,24 +1,95 @@
-use bevy::{input::mouse::MouseMotion, prelude::*};
-use heron::prelude::*;
+use bevy::prelude::*;
+use heron::{
+    prelude::*,
+    rapier_plugin::{PhysicsWorld, RayCastInfo, ShapeCastCollisionType},
+};
 
-use crate::terrain::TerrainCenter;
+use crate::{
+    controls::{Action, Controls},
+    parachute::Parachute,
+    plane::Plane,
+    terrain::TerrainCenter,
+    water::Water,
+    wingsuit::Wingsuit,
+};
 
 #[derive(Component, Clone, Debug)]
 pub struct Player {
+    /// Walking speed, in m/s.
     pub movement_speed: f32,
+    /// Speed while holding shift, in m/s.
+    pub sprint_speed: f32,
+    pub swim_speed: f32,
+    /// Vertical speed at the start of a jump, in m/s.
+    pub jump_speed: f32,
+    /// How fast the player reaches the speed they walk at, in m/s².
+    pub acceleration: f32,
+    /// Fraction of the acceleration available in the air.
+    pub air_control: f32,
+    /// Steepest slope the player can stand and walk on, in degrees.
+    pub max_slope: f32,
+    /// Highest ledge the player walks up without jumping.
+    pub step_height: f32,
+    /// Health from 0 to 1, the player is knocked out at 0.
+    pub health: f32,
+    /// Whether the player stands on walkable ground.
+    pub grounded: bool,
+    /// Velocity of the ground under the player's feet, kept while in the air
+    /// so steering is relative to the platform they jumped off.
+    pub platform_velocity: Vec3,
 }
 
 impl Default for Player {
     fn default() -> Self {
         Self {
             movement_speed: 3.0,
+            sprint_speed: 6.0,
+            swim_speed: 1.5,
+            jump_speed: 4.5,
+            acceleration: 25.0,
+            air_control: 0.2,
+            max_slope: 40.0,
+            step_height: 0.35,
+            health: 1.0,
+            grounded: false,
+            platform_velocity: Vec3::ZERO,
         }
     }
 }
 
+/// Depth below the surface the player floats at while swimming.
+const SWIM_DEPTH: f32 = 0.3;
+
+/// Distance from the center of the player to their feet.
+pub const HALF_HEIGHT: f32 = 0.75;
+
+/// Radius of the capsule of the player.
+const RADIUS: f32 = 0.25;
+
+/// How far below the feet ground is searched for.
+const GROUND_PROBE: f32 = 0.3;
+
+/// Mass of a player with their gear, in kg.
+pub const PLAYER_MASS: f32 = 90.0;
+
+/// How far below a candidate exit position the ground is searched for.
+const EXIT_DROP: f32 = 3.0;
+
 #[derive(Component, Clone, Debug, Default)]
 pub struct PlayerCamera;
 
+/// Where a player leaving a vehicle goes.
+#[derive(Clone, Copy, Debug)]
+pub enum Exit {
+    /// Standing on the ground.
+    Ground(Vec3),
+    /// Dropping out next to the vehicle, there is no ground below the exits.
+    Air(Vec3),
+    /// There is ground below the exits but no room to stand on it, the player
+    /// stays inside.
+    Blocked,
+}
+
 impl Player {
     pub fn spawn(self, commands: &mut Commands, transform: Transform) -> Entity {
         commands
@@ -27,14 +98,15 @@ impl Player {
             .insert(transform)
             .insert(GlobalTransform::identity())
             .insert(RigidBody::Dynamic)
-            .insert(CollisionShape::Capsule {
-                half_segment: 0.5,
-                radius: 0.25,
-            })
+            .insert(Self::collision_shape())
+            .insert(Wingsuit::default())
             .insert(Velocity::default())
             .insert(RotationConstraints::lock())
+            // movement on the ground is handled by the controller, without
+            // friction the player slides off slopes that are too steep
             .insert(PhysicMaterial {
                 restitution: 0.0,
+                friction: 0.0,
                 ..Default::default()
             })
             .insert(TerrainCenter)
@@ -49,59 +121,337 @@ impl Player {
             .id()
     }
 
+    pub fn collision_shape() -> CollisionShape {
+        CollisionShape::Capsule {
+            half_segment: HALF_HEIGHT - RADIUS,
+            radius: RADIUS,
+        }
+    }
+
+    /// Returns the ground right below the feet of a player at `position`,
+    /// the ray starts below the feet so it doesn't hit the player.
+    pub fn probe_ground(physics_world: &PhysicsWorld, position: Vec3) -> Option<RayCastInfo> {
+        let feet = position - Vec3::Y * (HALF_HEIGHT + 0.02);
+
+        physics_world.ray_cast(feet, Vec3::Y * -GROUND_PROBE, true)
+    }
+
+    pub fn is_grounded(physics_world: &PhysicsWorld, position: Vec3) -> bool {
+        Self::probe_ground(physics_world, position).is_some()
+    }
+
+    /// Returns the moving body `entity` belongs to, colliders built from a
+    /// scene are children of the body.
+    fn find_platform(
+        entity: Entity,
+        parent_query: &Query<&Parent>,
+        body_query: &Query<(&Velocity, &GlobalTransform, Option<&Plane>), Without<Player>>,
+    ) -> Option<Entity> {
+        let mut entity = entity;
+
+        loop {
+            if body_query.get(entity).is_ok() {
+                return Some(entity);
+            }
+
+            entity = parent_query.get(entity).ok()?.0;
+        }
+    }
+
+    /// Returns whether a ledge in `direction` is low enough to step onto,
+    /// `entity` is the player, whose own collider the rays start in.
+    fn can_step(
+        &self,
+        physics_world: &PhysicsWorld,
+        entity: Entity,
+        position: Vec3,
+        direction: Vec3,
+    ) -> bool {
+        let feet = position - Vec3::Y * (HALF_HEIGHT - 0.05);
+        let ray = direction * (RADIUS + 0.15);
+
+        let cast = |start: Vec3| {
+            physics_world.ray_cast_with_filter(
+                start,
+                ray,
+                true,
+                CollisionLayers::default(),
+                |other| other != entity,
+            )
+        };
+
+        // a slope the player can walk up isn't a ledge
+        let blocked_low = cast(feet).map_or(false, |hit| {
+            hit.normal.angle_between(Vec3::Y) > self.max_slope.to_radians()
+        });
+        let blocked_high = cast(feet + Vec3::Y * self.step_height).is_some();
+
+        blocked_low && !blocked_high
+    }
+
+    /// Returns where a player leaving a vehicle through `candidates` goes:
+    /// the first of them with ground below it and room to stand, moved down
+    /// onto the ground.
+    pub fn find_exit(physics_world: &PhysicsWorld, candidates: &[Vec3]) -> Exit {
+        let shape = Self::collision_shape();
+        let mut ground_below = false;
+
+        for candidate in candidates.iter().copied() {
+            let ground =
+                if let Some(hit) = physics_world.ray_cast(candidate, Vec3::Y * -EXIT_DROP, true) {
+                    hit
+                } else {
+                    continue;
+                };
+
+            ground_below = true;
+
+            let position = ground.collision_point + Vec3::Y * (HALF_HEIGHT + 0.05);
+
+            let blocked = matches!(
+                physics_world.shape_cast(&shape, position, Quat::IDENTITY, Vec3::Y * 0.01),
+                Some(ShapeCastCollisionType::AlreadyPenetrating(_))
+            );
+
+            if !blocked {
+                return Exit::Ground(position);
+            }
+        }
+
+        match candidates.first() {
+            Some(candidate) if !ground_below => Exit::Air(*candidate),
+            _ => Exit::Blocked,
+        }
+    }
+
+    /// Spawns the player taken out of a vehicle by `take_player` at its exit,
+    /// moving with the vehicle around `center_of_mass`, they bail out without
+    /// ground below the exit.
+    ///
+    /// Returns `None` without taking the player when every exit is blocked.
+    pub fn exit_vehicle(
+        commands: &mut Commands,
+        physics_world: &PhysicsWorld,
+        candidates: &[Vec3],
+        velocity: Option<&Velocity>,
+        center_of_mass: Vec3,
+        take_player: impl FnOnce() -> Player,
+    ) -> Option<Entity> {
+        let (translation, on_ground) = match Self::find_exit(physics_world, candidates) {
+            Exit::Ground(position) => (position, true),
+            Exit::Air(position) => (position, false),
+            Exit::Blocked => return None,
+        };
+
+        let player_entity = take_player().spawn(commands, Transform::from_translation(translation));
+
+        let velocity = velocity
+            .map(|velocity| {
+                let angular_velocity: Vec3 = velocity.angular.into();
+                let offset = translation - center_of_mass;
+
+                velocity.linear + Vec3::cross(angular_velocity, offset)
+            })
+            .unwrap_or_default();
+
+        if on_ground {
+            commands
+                .entity(player_entity)
+                .insert(Velocity::from_linear(velocity));
+        } else {
+            Parachute::bail_out(commands, player_entity, velocity);
+        }
+
+        Some(player_entity)
+    }
+
     pub fn system(
-        mut mouse_motion: EventReader<MouseMotion>,
-        key_input: Res<Input<KeyCode>>,
-        windows: Res<Windows>,
+        time: Res<Time>,
+        controls: Res<Controls>,
+        water: Res<Water>,
+        physics_world: PhysicsWorld,
         mut player_query: Query<
-            (&Player, &mut Velocity, &mut Transform, &GlobalTransform),
+            (
+                Entity,
+                &mut Player,
+                &mut Velocity,
+                &mut Transform,
+                &GlobalTransform,
+                Option<&Parachute>,
+                Option<&Wingsuit>,
+            ),
             Without<PlayerCamera>,
         >,
         mut camera_query: Query<&mut Transform, (With<PlayerCamera>, Without<Player>)>,
+        parent_query: Query<&Parent>,
+        body_query: Query<(&Velocity, &GlobalTransform, Option<&Plane>), Without<Player>>,
     ) {
-        let window = windows.primary();
-
-        let mut delta = Vec2::ZERO;
-
-        if window.cursor_locked() {
-            for event in mouse_motion.iter() {
-                delta -= event.delta;
-            }
-
-            delta /= 1000.0;
-        }
+        let delta = -controls.mouse_delta;
 
         if let Ok(mut transform) = camera_query.get_single_mut() {
             transform.rotate(Quat::from_rotation_x(delta.y));
         }
 
-        if let Ok((player, mut velocity, mut transform, global_transform)) =
-            player_query.get_single_mut()
+        if let Ok((
+            entity,
+            mut player,
+            mut velocity,
+            mut transform,
+            global_transform,
+            parachute,
+            wingsuit,
+        )) = player_query.get_single_mut()
         {
             let mut movement = Vec3::ZERO;
 
             transform.rotate(Quat::from_rotation_y(delta.x));
 
-            if key_input.pressed(KeyCode::W) {
+            // falling players are moved by their parachute or wingsuit
+            if parachute.is_some() || wingsuit.map_or(false, |wingsuit| wingsuit.flying) {
+                player.grounded = false;
+                player.platform_velocity = Vec3::ZERO;
+
+                return;
+            }
+
+            if controls.pressed(Action::MoveForward) {
                 movement -= global_transform.local_z();
             }
 
-            if key_input.pressed(KeyCode::S) {
+            if controls.pressed(Action::MoveBack) {
                 movement += global_transform.local_z();
             }
 
-            if key_input.pressed(KeyCode::A) {
+            if controls.pressed(Action::MoveLeft) {
                 movement -= global_transform.local_x();
             }
 
-            if key_input.pressed(KeyCode::D) {
+            if controls.pressed(Action::MoveRight) {
                 movement += global_transform.local_x();
             }
 
             movement = movement.normalize_or_zero();
 
-            velocity.linear.x = movement.x * player.movement_speed;
-            velocity.linear.z = movement.z * player.movement_speed;
+            let position = global_transform.translation;
+            let depth = water.depth(position);
+            let dt = time.delta_seconds();
+
+            if depth > 0.0 {
+                // cancel gravity and float towards the surface, or swim up
+                // and down
+                let vertical_speed = if controls.pressed(Action::Jump) {
+                    player.swim_speed
+                } else if controls.pressed(Action::SwimDown) {
+                    -player.swim_speed
+                } else {
+                    f32::clamp(
+                        (depth - SWIM_DEPTH) * 2.0,
+                        -player.swim_speed,
+                        player.swim_speed,
+                    )
+                };
+
+                let blend = f32::min(3.0 * dt, 1.0);
+
+                velocity.linear.y += 9.81 * dt;
+                velocity.linear.y += (vertical_speed - velocity.linear.y) * blend;
+
+                velocity.linear.x = movement.x * player.swim_speed;
+                velocity.linear.z = movement.z * player.swim_speed;
+
+                player.grounded = false;
+                player.platform_velocity = Vec3::ZERO;
+
+                return;
+            }
+
+            let ground = Self::probe_ground(&physics_world, position);
+            let ground_normal = ground.as_ref().map_or(Vec3::Y, |hit| hit.normal);
+            let walkable = ground_normal.angle_between(Vec3::Y) <= player.max_slope.to_radians();
+
+            // the ground may be a moving body, like the wing of a taxiing
+            // plane, its velocity is taken where the player stands
+            let platform = ground.as_ref().and_then(|hit| {
+                let platform = Self::find_platform(hit.entity, &parent_query, &body_query)?;
+                let (body_velocity, body_transform, plane) = body_query.get(platform).ok()?;
+
+                let center_of_mass = match plane {
+                    Some(plane) => *body_transform * plane.mass.center_of_mass,
+                    None => body_transform.translation,
+                };
+
+                let angular_velocity: Vec3 = body_velocity.angular.into();
+                let point_velocity = body_velocity.linear
+                    + Vec3::cross(angular_velocity, hit.collision_point - center_of_mass);
+
+                Some((point_velocity, angular_velocity))
+            });
+
+            let ground_velocity = platform.map_or(Vec3::ZERO, |(velocity, _)| velocity);
+
+            // a player moving away from the ground just jumped and isn't on
+            // it anymore
+            player.grounded = ground.is_some()
+                && walkable
+                && (velocity.linear - ground_velocity).dot(ground_normal) < 1.0;
+
+            if player.grounded {
+                player.platform_velocity = ground_velocity;
+
+                // turn along with a turning platform
+                if let Some((_, angular_velocity)) = platform {
+                    transform.rotate(Quat::from_rotation_y(angular_velocity.y * dt));
+                }
+            }
+
+            let speed = if controls.pressed(Action::Sprint) {
+                player.sprint_speed
+            } else {
+                player.movement_speed
+            };
+
+            if player.grounded {
+                // walk along the slope, holding the player in place against
+                // gravity
+                let along_slope = movement - ground_normal * movement.dot(ground_normal);
+                let target = player.platform_velocity + along_slope.normalize_or_zero() * speed;
+
+                let change = (target - velocity.linear).clamp_length_max(player.acceleration * dt);
+                velocity.linear += change;
+
+                if movement != Vec3::ZERO
+                    && player.can_step(&physics_world, entity, position, movement)
+                {
+                    let step_speed = f32::sqrt(2.0 * 9.81 * player.step_height);
+                    velocity.linear.y = f32::max(velocity.linear.y, step_speed);
+                }
+
+                if controls.just_pressed(Action::Jump) {
+                    velocity.linear.y = player.platform_velocity.y + player.jump_speed;
+                    player.grounded = false;
+                }
+            } else if movement != Vec3::ZERO {
+                // steer a little in the air, steep slopes slide the player
+                // down on their own
+                let relative = velocity.linear - player.platform_velocity;
+                let horizontal = Vec3::new(relative.x, 0.0, relative.z);
+                let change = (movement * speed - horizontal)
+                    .clamp_length_max(player.acceleration * player.air_control * dt);
+
+                velocity.linear.x += change.x;
+                velocity.linear.z += change.z;
+            }
+        }
+    }
+
+    /// Puts a knocked out player back at the start.
+    pub fn health_system(mut commands: Commands, query: Query<(Entity, &Player)>) {
+        for (entity, player) in query.iter() {
+            if player.health <= 0.0 {
+                commands.entity(entity).despawn_recursive();
+
+                Player::default().spawn(&mut commands, Transform::from_xyz(0.0, 20.0, 0.0));
+            }
         }
     }
 }
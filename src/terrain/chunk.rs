@@ -157,6 +157,7 @@ impl TerrainChunks {
                 chunk.lod = update.lod;
 
                 chunk.mesh = mesh.clone();
+                chunk.height_map = update.height_map;
 
                 let (mut mesh_handle, mut collision_shape) = query.get_mut(chunk.entity).unwrap();
 
@@ -164,7 +165,7 @@ impl TerrainChunks {
                 match *collision_shape {
                     CollisionShape::HeightField {
                         ref mut heights, ..
-                    } => *heights = update.height_map.heights,
+                    } => *heights = chunk.height_map.heights.clone(),
                     _ => unreachable!(),
                 }
             } else {
@@ -182,7 +183,7 @@ impl TerrainChunks {
                     .insert(RigidBody::Static)
                     .insert(CollisionShape::HeightField {
                         size: Vec2::splat(chunk_size),
-                        heights: update.height_map.heights,
+                        heights: update.height_map.heights.clone(),
                     })
                     .insert(PhysicMaterial {
                         restitution: 0.0,
@@ -194,6 +195,7 @@ impl TerrainChunks {
                     lod: update.lod,
                     mesh,
                     entity,
+                    height_map: update.height_map,
                 };
 
                 self.chunks.insert(update.position, chunk);
@@ -201,6 +203,22 @@ impl TerrainChunks {
         }
     }
 
+    /// Returns the key of the chunk containing `p`.
+    pub fn chunk_key(&self, p: Vec2) -> IVec2 {
+        let chunk_size = self.chunk_size as f32;
+
+        (Vec2::round(p / chunk_size) * chunk_size).as_ivec2()
+    }
+
+    /// Samples the terrain height and normal at `p`, if the chunk containing
+    /// it is loaded.
+    pub fn sample(&self, p: Vec2) -> Option<TerrainSample> {
+        let key = self.chunk_key(p);
+        let chunk = self.chunks.get(&key)?;
+
+        Some(chunk.height_map.sample(p - key.as_vec2()))
+    }
+
     pub fn system(
         mut commands: Commands,
         mut chunks: ResMut<TerrainChunks>,
@@ -237,6 +255,13 @@ pub struct TerrainChunk {
     pub lod: usize,
     pub mesh: Handle<Mesh>,
     pub entity: Entity,
+    pub height_map: HeightMap,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TerrainSample {
+    pub height: f32,
+    pub normal: Vec3,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
@@ -298,6 +323,42 @@ impl HeightMap {
         }
     }
 
+    /// Interpolates height and normal at `p`, relative to the center of the
+    /// height map.
+    pub fn sample(&self, p: Vec2) -> TerrainSample {
+        let last = (self.row_size - 1) as f32;
+        let factor = last / self.size;
+
+        let x = ((p.x + self.size / 2.0) * factor).clamp(0.0, last);
+        let z = ((p.y + self.size / 2.0) * factor).clamp(0.0, last);
+
+        let x_i = usize::min(x as usize, self.row_size - 2);
+        let z_i = usize::min(z as usize, self.row_size - 2);
+        let tx = x - x_i as f32;
+        let tz = z - z_i as f32;
+
+        let height = |x_i: usize, z_i: usize| self.heights[x_i][z_i];
+        let normal = |x_i: usize, z_i: usize| Vec3::from(self.normals[x_i][z_i]);
+
+        let bilinear = |a: f32, b: f32, c: f32, d: f32| {
+            a * (1.0 - tx) * (1.0 - tz) + b * tx * (1.0 - tz) + c * (1.0 - tx) * tz + d * tx * tz
+        };
+
+        TerrainSample {
+            height: bilinear(
+                height(x_i, z_i),
+                height(x_i + 1, z_i),
+                height(x_i, z_i + 1),
+                height(x_i + 1, z_i + 1),
+            ),
+            normal: (normal(x_i, z_i) * (1.0 - tx) * (1.0 - tz)
+                + normal(x_i + 1, z_i) * tx * (1.0 - tz)
+                + normal(x_i, z_i + 1) * (1.0 - tx) * tz
+                + normal(x_i + 1, z_i + 1) * tx * tz)
+                .normalize_or_zero(),
+        }
+    }
+
     pub fn generate_mesh(&self) -> Mesh {
         let mut positions = Vec::<[f32; 3]>::with_capacity(self.row_size * self.row_size);
         let mut normals = Vec::<[f32; 3]>::with_capacity(self.row_size * self.row_size);
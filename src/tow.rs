@@ -0,0 +1,316 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_prototype_debug_lines::DebugLines;
+use heron::prelude::*;
+
+use crate::{
+    controls::{Action, Controls},
+    plane::{Autopilot, Plane, PlaneDescriptor},
+    terrain::TerrainChunks,
+};
+
+/// Where a rope is attached to one of its ends.
+#[derive(Clone, Copy, Debug)]
+pub enum TowAttachment {
+    /// The tow hook of a plane, at its nose.
+    TowHook,
+    /// The hook a plane tows others with, at its tail.
+    TailHook,
+    /// A point in the local space of the entity.
+    Point(Vec3),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TowEnd {
+    pub entity: Entity,
+    pub attachment: TowAttachment,
+}
+
+/// Elastic rope between two entities, it only pulls while stretched.
+///
+/// Ends with a [`Plane`] are pulled by the rope, any other end is a fixed
+/// anchor.
+#[derive(Component, Clone, Debug)]
+pub struct TowRope {
+    /// The towed end first, then the end pulling it.
+    pub ends: [TowEnd; 2],
+    /// Length of the rope without tension.
+    pub length: f32,
+    /// Tension per meter of stretch, in N/m.
+    pub stiffness: f32,
+    /// Tension per m/s the rope is stretching at, in Ns/m.
+    pub damping: f32,
+    /// Tension at which the rope breaks, in N.
+    pub max_tension: f32,
+    /// Angle below the towed end at which the rope is released on its own,
+    /// in degrees.
+    pub auto_release_angle: Option<f32>,
+    /// Tension in the last update, in N.
+    pub tension: f32,
+}
+
+/// Ground winch reeling in the rope attached to it.
+#[derive(Component, Clone, Debug)]
+pub struct Winch {
+    /// Speed the rope is reeled in at, in m/s.
+    pub reel_speed: f32,
+    /// Length at which the winch stops and the rope is released.
+    pub min_length: f32,
+}
+
+/// Length of the rope used for an aerotow.
+const AEROTOW_ROPE_LENGTH: f32 = 40.0;
+
+/// Distance to the winch from the glider at the start of a winch launch.
+const WINCH_DISTANCE: f32 = 500.0;
+
+impl TowRope {
+    /// Rope for an aerotow behind another plane.
+    pub fn aerotow(glider: Entity, tug: Entity) -> Self {
+        Self {
+            ends: [
+                TowEnd {
+                    entity: glider,
+                    attachment: TowAttachment::TowHook,
+                },
+                TowEnd {
+                    entity: tug,
+                    attachment: TowAttachment::TailHook,
+                },
+            ],
+            length: AEROTOW_ROPE_LENGTH,
+            stiffness: 2000.0,
+            damping: 300.0,
+            max_tension: 8000.0,
+            auto_release_angle: None,
+            tension: 0.0,
+        }
+    }
+
+    /// Cable from a glider to a winch on the ground.
+    pub fn winch(glider: Entity, winch: Entity, length: f32) -> Self {
+        Self {
+            ends: [
+                TowEnd {
+                    entity: glider,
+                    attachment: TowAttachment::TowHook,
+                },
+                TowEnd {
+                    entity: winch,
+                    attachment: TowAttachment::Point(Vec3::ZERO),
+                },
+            ],
+            length,
+            stiffness: 4000.0,
+            damping: 500.0,
+            max_tension: 10000.0,
+            auto_release_angle: Some(70.0),
+            tension: 0.0,
+        }
+    }
+
+    pub fn system(
+        mut commands: Commands,
+        time: Res<Time>,
+        controls: Res<Controls>,
+        descriptors: Res<Assets<PlaneDescriptor>>,
+        mut lines: ResMut<DebugLines>,
+        mut rope_query: Query<(Entity, &mut TowRope)>,
+        mut body_query: Query<(&GlobalTransform, Option<&mut Velocity>, Option<&Plane>)>,
+        mut autopilot_query: Query<&mut Autopilot>,
+        winch_query: Query<&Winch>,
+    ) {
+        let dt = time.delta_seconds();
+
+        for (rope_entity, mut rope) in rope_query.iter_mut() {
+            let mut points = [Vec3::ZERO; 2];
+            let mut point_velocities = [Vec3::ZERO; 2];
+            let mut entered = false;
+            let mut missing = false;
+
+            for (i, end) in rope.ends.iter().enumerate() {
+                let (transform, velocity, plane) = if let Ok(c) = body_query.get(end.entity) {
+                    c
+                } else {
+                    missing = true;
+                    break;
+                };
+
+                let descriptor = plane.and_then(|plane| descriptors.get(&plane.descriptor));
+
+                let attachment = match (end.attachment, descriptor) {
+                    (TowAttachment::TowHook, Some(d)) => d.tow_hook(),
+                    (TowAttachment::TailHook, Some(d)) => d.tail_hook(),
+                    (TowAttachment::Point(point), _) => point,
+                    (_, None) => Vec3::ZERO,
+                };
+
+                points[i] = *transform * attachment;
+                entered |= plane.map_or(false, Plane::has_control);
+
+                if let Some(velocity) = velocity {
+                    let angular_velocity: Vec3 = velocity.angular.into();
+
+                    point_velocities[i] = velocity.linear
+                        + Vec3::cross(angular_velocity, points[i] - transform.translation);
+                }
+            }
+
+            let offset = points[1] - points[0];
+            let distance = offset.length();
+            let direction = offset.normalize_or_zero();
+
+            let stretch = distance - rope.length;
+            let stretch_rate = Vec3::dot(point_velocities[1] - point_velocities[0], direction);
+
+            rope.tension = if stretch > 0.0 {
+                f32::max(rope.stiffness * stretch + rope.damping * stretch_rate, 0.0)
+            } else {
+                0.0
+            };
+
+            if let Ok(winch) = winch_query.get(rope.ends[1].entity) {
+                rope.length = f32::max(rope.length - winch.reel_speed * dt, winch.min_length);
+            }
+
+            let winch_done = winch_query
+                .get(rope.ends[1].entity)
+                .map_or(false, |winch| rope.length <= winch.min_length);
+
+            let auto_released = rope.auto_release_angle.map_or(false, |angle| {
+                f32::atan2(-offset.y, offset.xz().length()) > angle.to_radians()
+            });
+
+            let released = entered && controls.just_pressed(Action::ReleaseRope);
+
+            if missing || released || auto_released || winch_done || rope.tension > rope.max_tension
+            {
+                commands.entity(rope_entity).despawn();
+
+                for end in rope.ends.iter() {
+                    if let Ok(mut autopilot) = autopilot_query.get_mut(end.entity) {
+                        autopilot.return_home();
+                    }
+
+                    if winch_query.get(end.entity).is_ok() {
+                        commands.entity(end.entity).despawn();
+                    }
+                }
+
+                continue;
+            }
+
+            // white while slack, turning red towards the breaking tension
+            let load = 1.0 - rope.tension / rope.max_tension;
+            let color = Color::rgb(1.0, load, load);
+
+            lines.line_colored(points[0], points[1], 0.0, color);
+
+            if rope.tension <= 0.0 {
+                continue;
+            }
+
+            for (i, end) in rope.ends.iter().enumerate() {
+                let (transform, velocity, plane) = if let Ok(c) = body_query.get_mut(end.entity) {
+                    c
+                } else {
+                    continue;
+                };
+
+                let (mut velocity, plane) = if let (Some(v), Some(p)) = (velocity, plane) {
+                    (v, p)
+                } else {
+                    continue;
+                };
+
+                if plane.mass.mass <= 0.0 {
+                    continue;
+                }
+
+                let force = if i == 0 { direction } else { -direction } * rope.tension;
+                let center_of_mass = *transform * plane.mass.center_of_mass;
+                let torque = Vec3::cross(points[i] - center_of_mass, force);
+
+                let angular_velocity: Vec3 = velocity.angular.into();
+
+                velocity.linear += force * dt / plane.mass.mass;
+                velocity.angular = From::from(angular_velocity + torque * dt / plane.mass.mass);
+            }
+        }
+    }
+
+    /// Starts an aerotow with G or a winch launch with H for the entered
+    /// plane.
+    pub fn launch_system(
+        mut commands: Commands,
+        controls: Res<Controls>,
+        asset_server: Res<AssetServer>,
+        terrain: Res<TerrainChunks>,
+        plane_query: Query<(Entity, &Plane, &Velocity, &GlobalTransform)>,
+        rope_query: Query<&TowRope>,
+    ) {
+        let aerotow = controls.just_pressed(Action::Aerotow);
+        let winch = controls.just_pressed(Action::WinchLaunch);
+
+        if !aerotow && !winch {
+            return;
+        }
+
+        let (glider, velocity, transform) = if let Some((entity, _, velocity, transform)) =
+            plane_query.iter().find(|(_, plane, _, _)| plane.entered)
+        {
+            (entity, velocity, transform)
+        } else {
+            return;
+        };
+
+        let towed = rope_query
+            .iter()
+            .any(|rope| rope.ends.iter().any(|end| end.entity == glider));
+
+        if towed {
+            return;
+        }
+
+        let forward = transform.local_z();
+
+        if aerotow {
+            let translation = transform.translation + forward * (AEROTOW_ROPE_LENGTH + 4.0);
+
+            let tug = Plane::default().spawn(
+                &mut commands,
+                &asset_server,
+                "planes/basic.plane.ron",
+                Transform::from_translation(translation).with_rotation(transform.rotation),
+            );
+
+            commands.entity(tug).insert(*velocity).insert(Autopilot {
+                heading: forward,
+                ..Default::default()
+            });
+
+            commands.spawn().insert(TowRope::aerotow(glider, tug));
+        } else {
+            let mut translation = transform.translation + forward * WINCH_DISTANCE;
+
+            if let Some(sample) = terrain.sample(translation.xz()) {
+                translation.y = sample.height;
+            }
+
+            let winch = commands
+                .spawn()
+                .insert(Transform::from_translation(translation))
+                .insert(GlobalTransform::from_translation(translation))
+                .insert(Winch {
+                    reel_speed: 25.0,
+                    min_length: 50.0,
+                })
+                .id();
+
+            let length = translation.distance(transform.translation);
+
+            commands
+                .spawn()
+                .insert(TowRope::winch(glider, winch, length));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+
+use crate::terrain::{TerrainCenter, TerrainChunks};
+
+/// Water covering everything below a fixed level.
+#[derive(Clone, Debug)]
+pub struct Water {
+    pub level: f32,
+    /// Density of the water, in kg/m³.
+    pub density: f32,
+    /// Speed into the water above which a plane is damaged, in m/s.
+    pub max_impact_speed: f32,
+}
+
+impl Default for Water {
+    fn default() -> Self {
+        Self {
+            level: 5.0,
+            density: 1000.0,
+            max_impact_speed: 8.0,
+        }
+    }
+}
+
+/// The rendered water surface, it follows the [`TerrainCenter`].
+#[derive(Component, Clone, Debug, Default)]
+pub struct WaterSurface;
+
+impl Water {
+    /// Returns how far `position` is below the surface, negative above it.
+    pub fn depth(&self, position: Vec3) -> f32 {
+        self.level - position.y
+    }
+
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        terrain: &TerrainChunks,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Entity {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Plane {
+                    size: terrain.max_range * 2.0,
+                })),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgba(0.1, 0.3, 0.45, 0.8),
+                    perceptual_roughness: 0.1,
+                    alpha_mode: AlphaMode::Blend,
+                    ..Default::default()
+                }),
+                transform: Transform::from_xyz(0.0, self.level, 0.0),
+                ..Default::default()
+            })
+            .insert(WaterSurface)
+            .id()
+    }
+
+    pub fn system(
+        water: Res<Water>,
+        center_query: Query<&GlobalTransform, With<TerrainCenter>>,
+        mut surface_query: Query<&mut Transform, With<WaterSurface>>,
+    ) {
+        let center = if let Some(transform) = center_query.iter().next() {
+            transform.translation
+        } else {
+            return;
+        };
+
+        for mut transform in surface_query.iter_mut() {
+            transform.translation = Vec3::new(center.x, water.level, center.z);
+        }
+    }
+}
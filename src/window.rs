@@ -1,18 +1,16 @@
 use bevy::prelude::*;
 
-pub fn window_system(
-    mut windows: ResMut<Windows>,
-    mouse_input: Res<Input<MouseButton>>,
-    key_input: Res<Input<KeyCode>>,
-) {
+use crate::controls::{Action, Controls};
+
+pub fn window_system(mut windows: ResMut<Windows>, controls: Res<Controls>) {
     let window = windows.primary_mut();
 
-    if key_input.just_pressed(KeyCode::Space) {
+    if controls.just_pressed(Action::LockCursor) {
         window.set_cursor_lock_mode(true);
         window.set_cursor_visibility(false);
     }
 
-    if key_input.just_pressed(KeyCode::Escape) {
+    if controls.just_pressed(Action::UnlockCursor) {
         window.set_cursor_lock_mode(false);
         window.set_cursor_visibility(true);
     }
@@ -0,0 +1,256 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::DebugLines;
+use heron::{prelude::*, rapier_plugin::PhysicsWorld};
+
+use crate::{
+    controls::{Action, Controls},
+    lift::LiftSources,
+    parachute::Parachute,
+    plane::{PlaneBody, PlaneSurface, SurfaceForces, SurfaceInputKind, SurfaceSide},
+    player::{Player, PLAYER_MASS},
+    terrain::TerrainChunks,
+    water::Water,
+};
+
+/// Fabric between the arms and legs of a player, flown by changing the pose
+/// of the body.
+///
+/// Surfaces and the body are laid out like those of a plane, with the head
+/// along +Z and the back along +Y.
+#[derive(Component, Clone, Debug)]
+pub struct Wingsuit {
+    pub flying: bool,
+    pub surfaces: Vec<PlaneSurface>,
+    pub body: PlaneBody,
+    /// Nose up attitude of the body, in radians.
+    pub pitch: f32,
+    /// Bank to the right, in radians.
+    pub bank: f32,
+    /// Attitude the flyer holds without input, in degrees.
+    pub trim_pitch: f32,
+    /// Change of attitude at full pitch input, in degrees.
+    pub max_pitch: f32,
+    /// Bank at full roll input, in degrees.
+    pub max_bank: f32,
+}
+
+impl Default for Wingsuit {
+    fn default() -> Self {
+        let fabric = PlaneSurface {
+            input_kind: SurfaceInputKind::None,
+            side: SurfaceSide::Center,
+            lift_slope: 3.5,
+            skin_friction: 0.04,
+            zero_lift_aoa: -2.0,
+            stall_angle_high: 25.0,
+            stall_angle_low: -20.0,
+            flap_fraction: 0.0,
+            ..Default::default()
+        };
+
+        Self {
+            flying: false,
+            surfaces: vec![
+                PlaneSurface {
+                    name: String::from("arm_wing"),
+                    position: Vec3::new(0.0, 0.0, 0.1),
+                    span: 1.8,
+                    chord: 0.6,
+                    ..fabric.clone()
+                },
+                PlaneSurface {
+                    name: String::from("leg_wing"),
+                    position: Vec3::new(0.0, 0.0, -0.6),
+                    span: 0.7,
+                    chord: 0.7,
+                    ..fabric
+                },
+            ],
+            body: PlaneBody {
+                name: String::from("body"),
+                frontal_area: 0.12,
+                side_area: 0.6,
+                drag_coefficient: 0.6,
+                side_drag_coefficient: 1.0,
+                ..Default::default()
+            },
+            pitch: 0.0,
+            bank: 0.0,
+            trim_pitch: -10.0,
+            max_pitch: 30.0,
+            max_bank: 40.0,
+        }
+    }
+}
+
+impl Wingsuit {
+    /// Returns the rotation of the flyer, from the rotation of the player
+    /// who faces along -Z.
+    pub fn rotation(&self, player_rotation: Quat) -> Quat {
+        player_rotation
+            * Quat::from_rotation_y(PI)
+            * Quat::from_rotation_x(-self.pitch)
+            * Quat::from_rotation_z(self.bank)
+    }
+
+    /// Sums the forces of all surfaces and the body, `air_velocity` is the
+    /// velocity of the air relative to the flyer.
+    pub fn calculate_forces(
+        &self,
+        air_velocity: Vec3,
+        air_density: f32,
+        position: Vec3,
+        rotation: Quat,
+        lines: &mut DebugLines,
+    ) -> SurfaceForces {
+        let mut forces = SurfaceForces::default();
+
+        for surface in self.surfaces.iter() {
+            let relative_position = rotation * surface.position;
+
+            let surface_forces = surface.calculate_forces(
+                air_velocity,
+                air_density,
+                relative_position,
+                position + relative_position,
+                rotation * surface.rotation_quat(),
+                0.0,
+                0.0,
+                None,
+                lines,
+            );
+
+            forces.linear += surface_forces.linear;
+            forces.angular += surface_forces.angular;
+        }
+
+        let relative_position = rotation * self.body.position;
+
+        let body_forces = self.body.calculate_forces(
+            air_velocity,
+            air_density,
+            relative_position,
+            position + relative_position,
+            rotation * self.body.rotation_quat(),
+            lines,
+        );
+
+        forces.linear += body_forces.linear;
+        forces.angular += body_forces.angular;
+
+        forces
+    }
+
+    /// Spreads the wings with F while falling, W and S pitch the body and A
+    /// and D bank it into a turn.
+    pub fn system(
+        time: Res<Time>,
+        controls: Res<Controls>,
+        lift_sources: Res<LiftSources>,
+        terrain: Res<TerrainChunks>,
+        water: Res<Water>,
+        physics_world: PhysicsWorld,
+        mut lines: ResMut<DebugLines>,
+        mut query: Query<
+            (
+                &mut Wingsuit,
+                &mut Velocity,
+                &mut Transform,
+                &GlobalTransform,
+                Option<&Parachute>,
+            ),
+            With<Player>,
+        >,
+    ) {
+        let elapsed = time.seconds_since_startup() as f32;
+        let dt = time.delta_seconds();
+
+        for (mut wingsuit, mut velocity, mut transform, global_transform, parachute) in
+            query.iter_mut()
+        {
+            let position = global_transform.translation;
+
+            let grounded =
+                Player::is_grounded(&physics_world, position) || water.depth(position) > 0.0;
+            let canopy_open = parachute.map_or(false, |parachute| parachute.inflation() > 0.0);
+
+            if controls.just_pressed(Action::ToggleWingsuit) && !grounded && !canopy_open {
+                wingsuit.flying = !wingsuit.flying;
+            }
+
+            if grounded || canopy_open {
+                wingsuit.flying = false;
+            }
+
+            if !wingsuit.flying {
+                wingsuit.pitch = 0.0;
+                wingsuit.bank = 0.0;
+
+                continue;
+            }
+
+            let mut pitch_input = 0.0;
+            let mut roll_input = 0.0;
+
+            // like in a plane, pushing forward lowers the head
+            if controls.pressed(Action::MoveForward) {
+                pitch_input += 1.0;
+            }
+
+            if controls.pressed(Action::MoveBack) {
+                pitch_input -= 1.0;
+            }
+
+            if controls.pressed(Action::MoveLeft) {
+                roll_input -= 1.0;
+            }
+
+            if controls.pressed(Action::MoveRight) {
+                roll_input += 1.0;
+            }
+
+            let target_pitch =
+                (wingsuit.trim_pitch - pitch_input * wingsuit.max_pitch).to_radians();
+            let target_bank = (roll_input * wingsuit.max_bank).to_radians();
+
+            // the body takes a moment to change its pose
+            let blend = f32::min(2.0 * dt, 1.0);
+
+            wingsuit.pitch += (target_pitch - wingsuit.pitch) * blend;
+            wingsuit.bank += (target_bank - wingsuit.bank) * blend;
+
+            // banking turns the flyer like a coordinated turn
+            let speed = velocity.linear.length();
+            let turn_rate = 9.81 * wingsuit.bank.tan() / f32::max(speed, 5.0);
+
+            transform.rotate(Quat::from_rotation_y(-turn_rate * dt));
+
+            let rotation = wingsuit.rotation(global_transform.rotation);
+
+            let air_density = f32::clamp(1.0 - (position.y / 1000.0), 0.0, 1.0);
+            let wind = lift_sources
+                .around(position, &terrain)
+                .air_velocity(position, elapsed);
+
+            let forces = wingsuit.calculate_forces(
+                wind - velocity.linear,
+                air_density,
+                position,
+                rotation,
+                &mut lines,
+            );
+
+            velocity.linear += forces.linear * dt / PLAYER_MASS;
+
+            if cfg!(feature = "debug") {
+                let transform = GlobalTransform::from_translation(position).with_rotation(rotation);
+
+                for surface in wingsuit.surfaces.iter() {
+                    surface.debug_draw(&transform, Color::BLUE, Color::YELLOW_GREEN, &mut lines);
+                }
+            }
+        }
+    }
+}